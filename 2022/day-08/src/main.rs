@@ -1,40 +1,65 @@
+use std::env;
+use std::fs;
 use std::ops::{Index, IndexMut};
 
+#[derive(Clone, Copy)]
 enum Direction {
     Left,
     Right,
     Top,
     Bottom,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Direction {
+    const ORTHOGONAL: [Direction; 4] = [
+        Direction::Left,
+        Direction::Right,
+        Direction::Top,
+        Direction::Bottom,
+    ];
+    const ALL: [Direction; 8] = [
+        Direction::Left,
+        Direction::Right,
+        Direction::Top,
+        Direction::Bottom,
+        Direction::TopLeft,
+        Direction::TopRight,
+        Direction::BottomLeft,
+        Direction::BottomRight,
+    ];
+    fn delta(&self) -> (isize, isize) {
+        match self {
+            Direction::Left => (0, -1),
+            Direction::Right => (0, 1),
+            Direction::Top => (-1, 0),
+            Direction::Bottom => (1, 0),
+            Direction::TopLeft => (-1, -1),
+            Direction::TopRight => (-1, 1),
+            Direction::BottomLeft => (1, -1),
+            Direction::BottomRight => (1, 1),
+        }
+    }
 }
 
 #[derive(Default)]
 struct VisibilityThreshold {
-    left: u32,
-    right: u32,
-    top: u32,
-    bottom: u32,
+    thresholds: [u32; 8],
 }
 
 impl Index<Direction> for VisibilityThreshold {
     type Output = u32;
     fn index(&self, direction: Direction) -> &Self::Output {
-        match direction {
-            Direction::Left => &self.left,
-            Direction::Right => &self.right,
-            Direction::Top => &self.top,
-            Direction::Bottom => &self.bottom,
-        }
+        &self.thresholds[direction as usize]
     }
 }
 
 impl IndexMut<Direction> for VisibilityThreshold {
     fn index_mut(&mut self, direction: Direction) -> &mut Self::Output {
-        match direction {
-            Direction::Left => &mut self.left,
-            Direction::Right => &mut self.right,
-            Direction::Top => &mut self.top,
-            Direction::Bottom => &mut self.bottom,
-        }
+        &mut self.thresholds[direction as usize]
     }
 }
 
@@ -42,74 +67,163 @@ impl VisibilityThreshold {
     fn new() -> Self {
         Default::default()
     }
-    fn allows_viewing(&self, height: u32) -> bool {
-        return height > self.left
-            || height > self.right
-            || height > self.top
-            || height > self.bottom;
+    fn allows_viewing(&self, height: u32, directions: &[Direction]) -> bool {
+        directions.iter().any(|&direction| height > self[direction])
+    }
+}
+
+fn step(
+    (rows, cols): (usize, usize),
+    (i, j): (usize, usize),
+    (di, dj): (isize, isize),
+) -> Option<(usize, usize)> {
+    let i = i.checked_add_signed(di)?;
+    let j = j.checked_add_signed(dj)?;
+    (i < rows && j < cols).then_some((i, j))
+}
+
+fn lines_along(size: (usize, usize), direction: Direction) -> Vec<Vec<(usize, usize)>> {
+    let (di, dj) = direction.delta();
+    let mut lines = Vec::new();
+    for i in 0..size.0 {
+        for j in 0..size.1 {
+            if step(size, (i, j), (-di, -dj)).is_some() {
+                continue;
+            }
+            let mut line = vec![(i, j)];
+            while let Some(next) = step(size, *line.last().unwrap(), (di, dj)) {
+                line.push(next);
+            }
+            lines.push(line);
+        }
+    }
+    lines
+}
+
+struct Bresenham {
+    current: (isize, isize),
+    target: (isize, isize),
+    delta: (isize, isize),
+    sign: (isize, isize),
+    err: isize,
+    done: bool,
+}
+
+impl Iterator for Bresenham {
+    type Item = (usize, usize);
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let (y, x) = self.current;
+        if self.current == self.target {
+            self.done = true;
+        } else {
+            let (dy, dx) = self.delta;
+            let e2 = 2 * self.err;
+            if e2 >= dy {
+                self.err += dy;
+                self.current.1 += self.sign.1;
+            }
+            if e2 <= dx {
+                self.err += dx;
+                self.current.0 += self.sign.0;
+            }
+        }
+        Some((y as usize, x as usize))
+    }
+}
+
+fn bresenham(from: (usize, usize), to: (usize, usize)) -> Bresenham {
+    let (from, to) = (from.min(to), from.max(to));
+    let (y, x) = (from.0 as isize, from.1 as isize);
+    let (y1, x1) = (to.0 as isize, to.1 as isize);
+    let delta = (-(y1 - y).abs(), (x1 - x).abs());
+    Bresenham {
+        current: (y, x),
+        target: (y1, x1),
+        delta,
+        sign: ((y1 - y).signum(), (x1 - x).signum()),
+        err: delta.0 + delta.1,
+        done: false,
     }
 }
 
 struct Forest {
     grid: Vec<Vec<u32>>,
+    directions: Vec<Direction>,
     visibilities: Vec<Vec<VisibilityThreshold>>,
+    scenic_scores: Vec<Vec<u64>>,
 }
 
 impl Forest {
     fn from_lines(input: &str) -> Self {
+        Self::with_directions(input, &Direction::ORTHOGONAL)
+    }
+    fn with_directions(input: &str, directions: &[Direction]) -> Self {
         let grid: Vec<Vec<u32>> = input
             .lines()
             .map(|line| line.chars().map(|c| c.to_digit(10).unwrap() + 1).collect())
             .collect();
-        let visibilities = Self::calculate_visibilities(&grid);
-        Self { grid, visibilities }
+        let visibilities = Self::calculate_visibilities(&grid, directions);
+        let scenic_scores = Self::calculate_scenic_scores(&grid, directions);
+        Self {
+            grid,
+            directions: directions.to_vec(),
+            visibilities,
+            scenic_scores,
+        }
     }
-    fn calculate_visibilities(grid: &Vec<Vec<u32>>) -> Vec<Vec<VisibilityThreshold>> {
-        let mut visibilities: Vec<Vec<VisibilityThreshold>> = Vec::new();
-        let rows = grid.len();
-        let cols = grid[0].len();
-
-        for i in 0..rows {
-            let mut vis_row: Vec<VisibilityThreshold> = Vec::new();
-            let vis_prev = visibilities.get(i.wrapping_sub(1));
-
-            for j in 0..cols {
-                let mut current_threshold = VisibilityThreshold::new();
-
-                current_threshold[Direction::Left] = match vis_row.get(j.wrapping_sub(1)) {
-                    Some(threshold) => threshold[Direction::Left].max(grid[i][j - 1]),
-                    None => 0,
-                };
-
-                current_threshold[Direction::Top] = match vis_prev {
-                    Some(top_row) => top_row[j][Direction::Top].max(grid[i - 1][j]),
-                    None => 0,
-                };
+    fn size(grid: &[Vec<u32>]) -> (usize, usize) {
+        (grid.len(), grid[0].len())
+    }
+    fn calculate_visibilities(
+        grid: &[Vec<u32>],
+        directions: &[Direction],
+    ) -> Vec<Vec<VisibilityThreshold>> {
+        let (rows, cols) = Self::size(grid);
+        let mut visibilities: Vec<Vec<VisibilityThreshold>> = (0..rows)
+            .map(|_| (0..cols).map(|_| VisibilityThreshold::new()).collect())
+            .collect();
 
-                vis_row.push(current_threshold);
+        for &direction in directions {
+            for line in lines_along((rows, cols), direction) {
+                let mut tallest = 0;
+                for &(i, j) in line.iter().rev() {
+                    visibilities[i][j][direction] = tallest;
+                    tallest = tallest.max(grid[i][j]);
+                }
             }
-            visibilities.push(vis_row);
         }
 
-        for i in (0..rows).rev() {
-            for j in (0..cols).rev() {
-                visibilities[i][j][Direction::Right] = match visibilities[i].get(j.wrapping_add(1))
-                {
-                    Some(threshold) => threshold[Direction::Right].max(grid[i][j + 1]),
-                    None => 0,
-                };
-
-                visibilities[i][j][Direction::Bottom] = match visibilities.get(i.wrapping_add(1)) {
-                    Some(vis_row) => vis_row[j][Direction::Bottom].max(grid[i + 1][j]),
-                    None => 0,
-                };
+        visibilities
+    }
+    fn calculate_scenic_scores(grid: &[Vec<u32>], directions: &[Direction]) -> Vec<Vec<u64>> {
+        let (rows, cols) = Self::size(grid);
+        let mut scores = vec![vec![1; cols]; rows];
+
+        for &direction in directions {
+            for line in lines_along((rows, cols), direction) {
+                let mut blockers: Vec<usize> = Vec::new();
+                for (k, &(i, j)) in line.iter().enumerate().rev() {
+                    while let Some(&top) = blockers.last() {
+                        let (bi, bj) = line[top];
+                        if grid[bi][bj] >= grid[i][j] {
+                            break;
+                        }
+                        blockers.pop();
+                    }
+                    let blocked_at = blockers.last().copied().unwrap_or(line.len() - 1);
+                    scores[i][j] *= (blocked_at - k) as u64;
+                    blockers.push(k);
+                }
             }
         }
 
-        visibilities
+        scores
     }
     fn is_visible(&self, i: usize, j: usize) -> bool {
-        return self.visibilities[i][j].allows_viewing(self.grid[i][j]);
+        self.visibilities[i][j].allows_viewing(self.grid[i][j], &self.directions)
     }
     fn number_visibles(&self) -> u32 {
         let mut count = 0;
@@ -122,66 +236,216 @@ impl Forest {
         }
         count
     }
-    fn scenic_score(&self, row: usize, col: usize) -> u32 {
-        let mut left = 0;
-        let mut right = 0;
-        let mut top = 0;
-        let mut bottom = 0;
-
-        for j in (0..col).rev() {
-            left += 1;
-            if self.grid[row][j] >= self.grid[row][col] {
-                break;
-            }
-        }
-
-        for j in col + 1..self.grid[row].len() {
-            right += 1;
-            if self.grid[row][j] >= self.grid[row][col] {
-                break;
-            }
-        }
-
-        for i in (0..row).rev() {
-            top += 1;
-            if self.grid[i][col] >= self.grid[row][col] {
-                break;
-            }
-        }
-
-        for i in row + 1..self.grid.len() {
-            bottom += 1;
-            if self.grid[i][col] >= self.grid[row][col] {
-                break;
-            }
-        }
-
-        return left * right * top * bottom;
-    }
-    fn best_scenic_score(&self) -> u32 {
-        let mut best = 0;
+    fn best_treehouse(&self) -> (usize, usize) {
+        let mut best = (0, 0);
         for i in 0..self.grid.len() {
             for j in 0..self.grid[i].len() {
-                best = best.max(self.scenic_score(i, j));
+                if self.scenic_scores[i][j] > self.scenic_scores[best.0][best.1] {
+                    best = (i, j);
+                }
             }
         }
         best
     }
+    fn best_scenic_score(&self) -> u64 {
+        let (i, j) = self.best_treehouse();
+        self.scenic_scores[i][j]
+    }
+    fn clear_line(&self, from: (usize, usize), to: (usize, usize), height: u32) -> bool {
+        bresenham(from, to)
+            .filter(|&cell| cell != from && cell != to)
+            .all(|(i, j)| self.grid[i][j] < height)
+    }
+    fn can_see(&self, from: (usize, usize), to: (usize, usize)) -> bool {
+        self.clear_line(from, to, self.grid[from.0][from.1])
+    }
+    fn viewshed(&self, from: (usize, usize)) -> Vec<(usize, usize)> {
+        self.viewshed_where(from, |_| true).collect()
+    }
+    fn viewshed_where<'a>(
+        &'a self,
+        from: (usize, usize),
+        keep: impl Fn((usize, usize)) -> bool + 'a,
+    ) -> impl Iterator<Item = (usize, usize)> + 'a {
+        let (rows, cols) = Self::size(&self.grid);
+        (0..rows)
+            .flat_map(move |i| (0..cols).map(move |j| (i, j)))
+            .filter(move |&to| to != from && keep(to) && self.can_see(from, to))
+    }
+    /// `bresenham` orders its endpoints, so both trees of a pair look along the
+    /// same cells and they see each other exactly when the shorter one sees the
+    /// taller one (ties broken by position).
+    fn mutually_visible_pairs(&self) -> usize {
+        let (rows, cols) = Self::size(&self.grid);
+        let height = |(i, j): (usize, usize)| self.grid[i][j];
+        (0..rows)
+            .flat_map(|i| (0..cols).map(move |j| (i, j)))
+            .map(|tree| {
+                self.viewshed_where(tree, |other| (height(other), other) > (height(tree), tree))
+                    .count()
+            })
+            .sum()
+    }
+    fn shade(height: u32) -> u32 {
+        (height - 1) * 255 / 9
+    }
+    fn to_pgm(&self) -> String {
+        let (rows, cols) = Self::size(&self.grid);
+        let mut image = format!("P2\n{} {}\n9\n", cols, rows);
+        for row in &self.grid {
+            let line: Vec<String> = row.iter().map(|h| (h - 1).to_string()).collect();
+            image += &line.join(" ");
+            image += "\n";
+        }
+        image
+    }
+    fn to_ppm(&self) -> String {
+        let (rows, cols) = Self::size(&self.grid);
+        let treehouse = self.best_treehouse();
+        let mut image = format!("P3\n{} {}\n255\n", cols, rows);
+        for i in 0..rows {
+            let line: Vec<String> = (0..cols)
+                .map(|j| {
+                    let level = Self::shade(self.grid[i][j]);
+                    let (r, g, b) = if (i, j) == treehouse {
+                        (255, 0, 0)
+                    } else if self.is_visible(i, j) {
+                        (level / 2, 127 + level / 2, level / 2)
+                    } else {
+                        (level, level, level)
+                    };
+                    format!("{} {} {}", r, g, b)
+                })
+                .collect();
+            image += &line.join(" ");
+            image += "\n";
+        }
+        image
+    }
+}
+
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|pos| args.get(pos + 1))
+        .map(|value| value.as_str())
+}
+
+fn part_one(file_contents: &str) -> u32 {
+    Forest::from_lines(file_contents).number_visibles()
+}
+
+fn part_two(file_contents: &str) -> u64 {
+    Forest::from_lines(file_contents).best_scenic_score()
 }
 
 fn main() {
     let file_contents = include_str!("input");
-    part_one(file_contents);
+    println!("{}", part_one(file_contents));
     println!();
-    part_two(file_contents);
-}
+    println!("{}", part_two(file_contents));
 
-fn part_one(file_contents: &str) {
-    let forest = Forest::from_lines(file_contents);
-    println!("{}", forest.number_visibles());
+    let args: Vec<String> = env::args().skip(1).collect();
+    let forest = if args.iter().any(|arg| arg == "--diagonals") {
+        let forest = Forest::with_directions(file_contents, &Direction::ALL);
+        println!();
+        println!("{}", forest.number_visibles());
+        println!();
+        println!("{}", forest.best_scenic_score());
+        forest
+    } else {
+        Forest::from_lines(file_contents)
+    };
+    if let Some(tree) = option_value(&args, "--viewshed") {
+        let (i, j) = tree.split_once(',').unwrap();
+        let visible = forest.viewshed((i.parse().unwrap(), j.parse().unwrap()));
+        println!();
+        println!("{}", visible.len());
+    }
+    if args.iter().any(|arg| arg == "--mutual-pairs") {
+        println!();
+        println!("{}", forest.mutually_visible_pairs());
+    }
+    if let Some(path) = option_value(&args, "--pgm") {
+        fs::write(path, forest.to_pgm()).unwrap();
+    }
+    if let Some(path) = option_value(&args, "--ppm") {
+        fs::write(path, forest.to_ppm()).unwrap();
+    }
 }
 
-fn part_two(file_contents: &str) {
-    let forest = Forest::from_lines(file_contents);
-    println!("{}", forest.best_scenic_score());
+#[cfg(test)]
+mod tests {
+    use super::*;
+    const INPUT: &str = "30373
+25512
+65332
+33549
+35390";
+
+    #[test]
+    fn part_one_example() {
+        assert_eq!(part_one(INPUT), 21);
+    }
+
+    #[test]
+    fn part_two_example() {
+        assert_eq!(part_two(INPUT), 8);
+        assert_eq!(Forest::from_lines(INPUT).best_treehouse(), (3, 2));
+    }
+
+    #[test]
+    fn diagonals() {
+        let forest = Forest::with_directions(INPUT, &Direction::ALL);
+        assert_eq!(forest.number_visibles(), 22);
+        assert_eq!(forest.scenic_scores[1][2], 8);
+        assert_eq!(forest.best_scenic_score(), 16);
+    }
+
+    #[test]
+    fn line_of_sight() {
+        let forest = Forest::from_lines(INPUT);
+        assert_eq!(
+            bresenham((0, 0), (2, 4)).collect::<Vec<_>>(),
+            [(0, 0), (1, 1), (1, 2), (2, 3), (2, 4)]
+        );
+        assert!(forest.can_see((3, 2), (1, 2)));
+        assert!(!forest.can_see((3, 2), (0, 2)));
+        assert!(forest.can_see((2, 0), (2, 3)));
+        assert!(!forest.can_see((2, 3), (2, 0)));
+        assert_eq!(forest.viewshed((2, 2)).len(), 9);
+        assert_eq!(forest.mutually_visible_pairs(), 106);
+    }
+
+    #[test]
+    fn mutual_visibility_on_a_larger_forest() {
+        let input: String = (0..12)
+            .map(|i| {
+                (0..12).map(move |j| char::from(b'0' + ((i * 7 + j * j * 3 + i * j) % 10) as u8))
+            })
+            .map(|row| row.collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let forest = Forest::from_lines(&input);
+        let trees: Vec<(usize, usize)> =
+            (0..12).flat_map(|i| (0..12).map(move |j| (i, j))).collect();
+        let mut expected = 0;
+        for (k, &a) in trees.iter().enumerate() {
+            for &b in &trees[k + 1..] {
+                if forest.can_see(a, b) && forest.can_see(b, a) {
+                    expected += 1;
+                }
+            }
+        }
+        assert!(expected > 0);
+        assert_eq!(forest.mutually_visible_pairs(), expected);
+    }
+
+    #[test]
+    fn heightmap() {
+        let forest = Forest::from_lines(INPUT);
+        assert!(forest.to_pgm().starts_with("P2\n5 5\n9\n3 0 3 7 3\n"));
+        let ppm = forest.to_ppm();
+        assert_eq!(ppm.lines().nth(6).unwrap().split(' ').nth(6), Some("255"));
+    }
 }
@@ -1,22 +1,96 @@
-use std::collections::HashSet;
-use std::convert::TryInto;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Write};
 use std::str::FromStr;
 
 fn main() {
     let file_contents = include_str!("input");
-    part_one(file_contents);
+    println!("{}", part_one(file_contents));
     println!();
-    part_two(file_contents);
+    println!("{}", part_two(file_contents));
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() {
+        return;
+    }
+    let knots = option_value(&args, "--knots").map_or(10, |v| v.parse().unwrap());
+    let every = match option_value(&args, "--every").map_or(Ok(1), parse_every) {
+        Ok(every) => every,
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
+    let model = match option_value(&args, "--slack") {
+        Some(slack) => Rope::with_slack(slack.split(',').map(|v| v.parse().unwrap()).collect()),
+        None => Rope::new(knots),
+    };
+    let simulation = Simulation::run(&model, &parse_instructions(file_contents));
+    if let Some(knot) = option_value(&args, "--visited") {
+        println!();
+        println!("{}", simulation.visited(knot.parse().unwrap()));
+    }
+    let heatmap_knot =
+        option_value(&args, "--heatmap").map_or(simulation.tail(), |v| v.parse().unwrap());
+    if option_value(&args, "--heatmap").is_some() {
+        println!();
+        println!("{}", simulation.heatmap(heatmap_knot));
+    }
+    if let Some(path) = option_value(&args, "--heatmap-csv") {
+        fs::write(path, simulation.heatmap_csv(heatmap_knot)).unwrap();
+    }
+    if args.iter().any(|arg| arg == "--stats") {
+        println!();
+        print!("{}", simulation.stats_table());
+    }
+    if let Some(path) = option_value(&args, "--stats-csv") {
+        fs::write(path, simulation.stats_csv()).unwrap();
+    }
+    if let Some(frame) = option_value(&args, "--frame") {
+        println!();
+        println!("{}", simulation.render_frame(frame.parse().unwrap()));
+    }
+    if let Some(path) = option_value(&args, "--cast") {
+        fs::write(path, simulation.to_cast(every)).unwrap();
+    }
+    if let Some(path) = option_value(&args, "--gif") {
+        simulation
+            .write_gif(File::create(path).unwrap(), every)
+            .unwrap();
+    }
+}
+
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|pos| args.get(pos + 1))
+        .map(|value| value.as_str())
 }
 
-#[derive(Clone, Hash, Eq, PartialEq, Debug)]
+fn parse_every(value: &str) -> Result<usize, String> {
+    match value.parse() {
+        Ok(0) => Err("--every must be at least 1".to_string()),
+        Ok(every) => Ok(every),
+        Err(err) => Err(format!("invalid --every {:?}: {}", value, err)),
+    }
+}
+
+#[derive(Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd, Debug)]
 struct Position {
     x: i32,
     y: i32,
+    z: i32,
 }
 impl Position {
     fn new() -> Self {
-        Position { x: 0, y: 0 }
+        Position { x: 0, y: 0, z: 0 }
+    }
+    fn shift(&mut self, delta: Position) {
+        self.x += delta.x;
+        self.y += delta.y;
+        self.z += delta.z;
     }
 }
 type Instruction = (Direction, usize);
@@ -26,6 +100,30 @@ enum Direction {
     Right,
     Down,
     Left,
+    UpRight,
+    UpLeft,
+    DownRight,
+    DownLeft,
+    Forward,
+    Backward,
+}
+
+impl Direction {
+    fn delta(&self) -> Position {
+        let (x, y, z) = match self {
+            Direction::Up => (0, 1, 0),
+            Direction::Right => (1, 0, 0),
+            Direction::Down => (0, -1, 0),
+            Direction::Left => (-1, 0, 0),
+            Direction::UpRight => (1, 1, 0),
+            Direction::UpLeft => (-1, 1, 0),
+            Direction::DownRight => (1, -1, 0),
+            Direction::DownLeft => (-1, -1, 0),
+            Direction::Forward => (0, 0, 1),
+            Direction::Backward => (0, 0, -1),
+        };
+        Position { x, y, z }
+    }
 }
 
 impl FromStr for Direction {
@@ -36,77 +134,420 @@ impl FromStr for Direction {
             "R" => Ok(Direction::Right),
             "D" => Ok(Direction::Down),
             "L" => Ok(Direction::Left),
+            "UR" => Ok(Direction::UpRight),
+            "UL" => Ok(Direction::UpLeft),
+            "DR" => Ok(Direction::DownRight),
+            "DL" => Ok(Direction::DownLeft),
+            "F" => Ok(Direction::Forward),
+            "B" => Ok(Direction::Backward),
             _ => Err(()),
         }
     }
 }
 
-fn update_knot(knots: &mut [Position]) {
+fn parse_instructions(file_contents: &str) -> Vec<Instruction> {
+    file_contents
+        .lines()
+        .map(|line| {
+            let mut split = line.split(" ");
+            (
+                Direction::from_str(split.next().unwrap()).unwrap(),
+                split.next().unwrap().parse::<usize>().unwrap(),
+            )
+        })
+        .collect()
+}
+
+struct Rope {
+    slack: Vec<i32>,
+}
+
+impl Rope {
+    fn new(knots: usize) -> Self {
+        Self::with_slack(vec![1; knots.saturating_sub(1)])
+    }
+    fn with_slack(slack: Vec<i32>) -> Self {
+        Self { slack }
+    }
+    fn knots(&self) -> usize {
+        self.slack.len() + 1
+    }
+}
+
+fn update_knot(knots: &mut [Position], slack: i32) {
     if let [front_knot, back_knot] = knots {
         let dx = front_knot.x - back_knot.x;
         let dy = front_knot.y - back_knot.y;
-        if dx.abs() > 1 {
-            back_knot.x += dx / dx.abs();
-            if dy.abs() > 1 {
-                back_knot.y += dy / dy.abs();
-            } else if dy.abs() > 0 {
-                back_knot.y += dy;
-            }
-        } else if dy.abs() > 1 {
-            back_knot.y += dy / dy.abs();
-            if dx.abs() > 1 {
-                back_knot.x += dx / dx.abs();
-            } else if dx.abs() > 0 {
-                back_knot.x += dx;
-            }
+        let dz = front_knot.z - back_knot.z;
+        if dx.abs().max(dy.abs()).max(dz.abs()) > slack {
+            back_knot.shift(Position {
+                x: dx.signum(),
+                y: dy.signum(),
+                z: dz.signum(),
+            });
         }
     }
 }
 
-fn print_rope(rope: &[Position], grid_len: usize) {
-    let mut grid = vec![vec!['.'; grid_len]; grid_len];
-    for (pos, knot) in rope.iter().enumerate() {
-        grid[grid_len - 1 - knot.y as usize][knot.x as usize] =
-            std::char::from_digit(pos.try_into().unwrap(), 10).unwrap();
+struct Simulation {
+    frames: Vec<Vec<Position>>,
+    bounds: (Position, Position),
+    visits: Vec<HashMap<Position, u32>>,
+    first_moved: Vec<Option<usize>>,
+}
+
+impl Simulation {
+    fn run(model: &Rope, instructions: &[Instruction]) -> Self {
+        let mut rope = vec![Position::new(); model.knots()];
+        let mut frames = vec![rope.clone()];
+        let mut visits = vec![HashMap::from([(Position::new(), 1)]); rope.len()];
+        let mut first_moved = vec![None; rope.len()];
+        for (direction, steps) in instructions {
+            for _ in 0..*steps {
+                rope[0].shift(direction.delta());
+                for (front, &slack) in model.slack.iter().enumerate() {
+                    update_knot(&mut rope[front..front + 2], slack);
+                }
+                let step = frames.len();
+                for (knot, position) in rope.iter().enumerate() {
+                    *visits[knot].entry(*position).or_insert(0) += 1;
+                    if first_moved[knot].is_none() && *position != Position::new() {
+                        first_moved[knot] = Some(step);
+                    }
+                }
+                frames.push(rope.clone());
+            }
+        }
+        let bounds = Self::bounds(frames.iter().flatten());
+        Self {
+            frames,
+            bounds,
+            visits,
+            first_moved,
+        }
+    }
+    fn visited(&self, knot: usize) -> usize {
+        self.visits[knot].len()
+    }
+    fn knot_bounds(&self, knot: usize) -> (Position, Position) {
+        Self::bounds(self.visits[knot].keys())
     }
-    for char_line in grid {
-        for character in char_line {
-            print!("{}", character);
+    fn tail(&self) -> usize {
+        self.frames[0].len() - 1
+    }
+    fn bounds<'a>(positions: impl Iterator<Item = &'a Position>) -> (Position, Position) {
+        let mut min = Position::new();
+        let mut max = Position::new();
+        for knot in positions {
+            min.x = min.x.min(knot.x);
+            min.y = min.y.min(knot.y);
+            min.z = min.z.min(knot.z);
+            max.x = max.x.max(knot.x);
+            max.y = max.y.max(knot.y);
+            max.z = max.z.max(knot.z);
         }
-        println!();
+        (min, max)
     }
-    println!("----------");
-}
-
-fn solve_for(rope_length: usize, file_contents: &str) {
-    let mut visited_positions = HashSet::new();
-    let mut rope = vec![Position::new(); rope_length];
-    for line in file_contents.lines() {
-        let mut split = line.split(" ");
-        let instruction: Instruction = (
-            Direction::from_str(split.next().unwrap()).unwrap(),
-            split.next().unwrap().parse::<usize>().unwrap(),
+    fn heatmap(&self, knot: usize) -> String {
+        const SHADES: &[u8] = b" .:-=+*#%@";
+        let (min, max) = self.knot_bounds(knot);
+        let mut counts: HashMap<(i32, i32), u32> = HashMap::new();
+        for (position, visits) in &self.visits[knot] {
+            *counts.entry((position.x, position.y)).or_insert(0) += visits;
+        }
+        let busiest = *counts.values().max().unwrap();
+        (min.y..=max.y)
+            .rev()
+            .map(|y| {
+                (min.x..=max.x)
+                    .map(|x| match counts.get(&(x, y)) {
+                        Some(&visits) => {
+                            let shade = (visits * 9).div_ceil(busiest) as usize;
+                            SHADES[shade] as char
+                        }
+                        None => SHADES[0] as char,
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+    fn heatmap_csv(&self, knot: usize) -> String {
+        let mut cells: Vec<(&Position, &u32)> = self.visits[knot].iter().collect();
+        cells.sort();
+        let mut csv = "x,y,z,visits\n".to_string();
+        for (position, visits) in cells {
+            csv += &format!("{},{},{},{}\n", position.x, position.y, position.z, visits);
+        }
+        csv
+    }
+    fn stats(&self) -> Vec<[String; 9]> {
+        (0..self.visits.len())
+            .map(|knot| {
+                let (min, max) = self.knot_bounds(knot);
+                [
+                    knot.to_string(),
+                    self.visited(knot).to_string(),
+                    min.x.to_string(),
+                    min.y.to_string(),
+                    min.z.to_string(),
+                    max.x.to_string(),
+                    max.y.to_string(),
+                    max.z.to_string(),
+                    self.first_moved[knot].map_or("-".to_string(), |step| step.to_string()),
+                ]
+            })
+            .collect()
+    }
+    fn stats_table(&self) -> String {
+        let mut table = format!(
+            "{:>5} {:>8} {:>20} {:>20} {:>12}\n",
+            "knot", "visited", "min", "max", "first moved"
+        );
+        for [knot, visited, min_x, min_y, min_z, max_x, max_y, max_z, first_moved] in self.stats() {
+            table += &format!(
+                "{:>5} {:>8} {:>20} {:>20} {:>12}\n",
+                knot,
+                visited,
+                format!("({},{},{})", min_x, min_y, min_z),
+                format!("({},{},{})", max_x, max_y, max_z),
+                first_moved
+            );
+        }
+        table
+    }
+    fn stats_csv(&self) -> String {
+        let mut csv = "knot,visited,min_x,min_y,min_z,max_x,max_y,max_z,first_moved\n".to_string();
+        for row in self.stats() {
+            csv += &row.join(",");
+            csv += "\n";
+        }
+        csv
+    }
+    fn knot_char(index: usize) -> char {
+        match index {
+            0 => 'H',
+            _ => std::char::from_digit(index as u32, 36).unwrap_or('*'),
+        }
+    }
+    fn draw(&self, rope: &[Position], trail: &HashSet<Position>) -> Vec<Vec<char>> {
+        let (min, max) = self.bounds;
+        let width = (max.x - min.x + 1) as usize;
+        let height = (max.y - min.y + 1) as usize;
+        let cell = |pos: &Position| ((max.y - pos.y) as usize, (pos.x - min.x) as usize);
+        let mut grid = vec![vec!['.'; width]; height];
+        for pos in trail {
+            let (row, col) = cell(pos);
+            grid[row][col] = '#';
+        }
+        let (row, col) = cell(&Position::new());
+        grid[row][col] = 's';
+        for (index, knot) in rope.iter().enumerate().rev() {
+            let (row, col) = cell(knot);
+            grid[row][col] = Self::knot_char(index);
+        }
+        grid
+    }
+    fn render_frame(&self, frame: usize) -> String {
+        let tail = self.tail();
+        let trail = self.frames[..=frame]
+            .iter()
+            .map(|rope| rope[tail])
+            .collect();
+        self.draw(&self.frames[frame], &trail)
+            .iter()
+            .map(|row| row.iter().collect::<String>())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+    fn sampled_frames(
+        &self,
+        every: usize,
+    ) -> impl Iterator<Item = (usize, HashSet<Position>)> + '_ {
+        let tail = self.tail();
+        let mut trail = HashSet::new();
+        let last = self.frames.len() - 1;
+        self.frames
+            .iter()
+            .enumerate()
+            .filter_map(move |(index, rope)| {
+                trail.insert(rope[tail]);
+                (index % every == 0 || index == last).then(|| (index, trail.clone()))
+            })
+    }
+    fn to_cast(&self, every: usize) -> String {
+        let (min, max) = self.bounds;
+        let mut cast = format!(
+            "{{\"version\": 2, \"width\": {}, \"height\": {}}}\n",
+            max.x - min.x + 1,
+            max.y - min.y + 1
         );
-        for _ in 0..instruction.1 {
-            match instruction.0 {
-                Direction::Up => rope[0].y += 1,
-                Direction::Right => rope[0].x += 1,
-                Direction::Down => rope[0].y -= 1,
-                Direction::Left => rope[0].x -= 1,
+        for (step, (index, trail)) in self.sampled_frames(every).enumerate() {
+            let screen = self
+                .draw(&self.frames[index], &trail)
+                .iter()
+                .map(|row| row.iter().collect::<String>())
+                .collect::<Vec<String>>()
+                .join("\\r\\n");
+            cast += &format!(
+                "[{:.2}, \"o\", \"\\u001b[H\\u001b[2J{}\"]\n",
+                step as f64 * 0.05,
+                screen
+            );
+        }
+        cast
+    }
+    fn write_gif<W: Write>(&self, writer: W, every: usize) -> Result<(), gif::EncodingError> {
+        const SCALE: usize = 4;
+        let palette = [
+            0x10, 0x10, 0x18, // air
+            0x60, 0x60, 0x70, // trail
+            0x40, 0x80, 0xff, // start
+            0xff, 0xd0, 0x40, // knot
+            0xff, 0x40, 0x40, // head
+        ];
+        let (min, max) = self.bounds;
+        let width = (max.x - min.x + 1) as usize * SCALE;
+        let height = (max.y - min.y + 1) as usize * SCALE;
+        let (gif_width, gif_height) = match (u16::try_from(width), u16::try_from(height)) {
+            (Ok(gif_width), Ok(gif_height)) => (gif_width, gif_height),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{}x{} frames are too large for a GIF", width, height),
+                )
+                .into())
             }
-            for front in 0..=(rope.len().saturating_sub(2)) {
-                update_knot(&mut rope[front..front + 2]);
+        };
+        let mut encoder = gif::Encoder::new(writer, gif_width, gif_height, &palette)?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+        for (index, trail) in self.sampled_frames(every) {
+            let grid = self.draw(&self.frames[index], &trail);
+            let mut pixels = vec![0u8; width * height];
+            for (y, pixel_row) in pixels.chunks_mut(width).enumerate() {
+                for (x, pixel) in pixel_row.iter_mut().enumerate() {
+                    *pixel = match grid[y / SCALE][x / SCALE] {
+                        '.' => 0,
+                        '#' => 1,
+                        's' => 2,
+                        'H' => 4,
+                        _ => 3,
+                    };
+                }
             }
-            visited_positions.insert(rope.last().unwrap().clone());
+            let mut frame = gif::Frame {
+                width: gif_width,
+                height: gif_height,
+                buffer: Cow::Owned(pixels),
+                ..Default::default()
+            };
+            frame.delay = 5;
+            encoder.write_frame(&frame)?;
         }
+        Ok(())
     }
-    println!("{}", visited_positions.len());
 }
 
-fn part_one(file_contents: &str) {
-    solve_for(2, file_contents);
+fn solve_for(rope_length: usize, file_contents: &str) -> usize {
+    let simulation = Simulation::run(&Rope::new(rope_length), &parse_instructions(file_contents));
+    simulation.visited(simulation.tail())
+}
+
+fn part_one(file_contents: &str) -> usize {
+    solve_for(2, file_contents)
+}
+
+fn part_two(file_contents: &str) -> usize {
+    solve_for(10, file_contents)
 }
 
-fn part_two(file_contents: &str) {
-    solve_for(10, file_contents);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    const INPUT: &str = "R 4
+U 4
+L 3
+D 1
+R 4
+D 1
+L 5
+R 2";
+    const LARGER_INPUT: &str = "R 5
+U 8
+L 8
+D 3
+R 17
+D 10
+L 25
+U 20";
+
+    #[test]
+    fn part_one_example() {
+        assert_eq!(part_one(INPUT), 13);
+    }
+
+    #[test]
+    fn part_two_example() {
+        assert_eq!(part_two(INPUT), 1);
+        assert_eq!(part_two(LARGER_INPUT), 36);
+    }
+
+    #[test]
+    fn generalized_rules() {
+        let diagonal = Simulation::run(&Rope::new(2), &parse_instructions("UR 3"));
+        assert_eq!(diagonal.visited(1), 3);
+        let slack = Simulation::run(&Rope::with_slack(vec![2]), &parse_instructions("R 4"));
+        assert_eq!(slack.visited(1), 3);
+        let spatial = Simulation::run(&Rope::new(2), &parse_instructions("F 3\nU 2"));
+        assert_eq!(spatial.visited(1), 4);
+        assert_eq!(
+            spatial.frames.last().unwrap()[1],
+            Position { x: 0, y: 1, z: 3 }
+        );
+        let long = Simulation::run(&Rope::new(10), &parse_instructions(INPUT));
+        assert_eq!(long.visited(1), part_one(INPUT));
+    }
+
+    #[test]
+    fn visit_statistics() {
+        let simulation = Simulation::run(&Rope::new(2), &parse_instructions("R 4\nL 4"));
+        assert_eq!(simulation.visits[1][&Position { x: 2, y: 0, z: 0 }], 2);
+        assert_eq!(simulation.first_moved, [Some(1), Some(2)]);
+        assert_eq!(simulation.heatmap(1), "***@");
+        assert_eq!(
+            simulation.heatmap_csv(1),
+            "x,y,z,visits\n0,0,0,2\n1,0,0,2\n2,0,0,2\n3,0,0,3\n"
+        );
+        assert_eq!(
+            simulation.stats_csv(),
+            "knot,visited,min_x,min_y,min_z,max_x,max_y,max_z,first_moved\n\
+             0,5,0,0,0,4,0,0,1\n\
+             1,4,0,0,0,3,0,0,2\n"
+        );
+    }
+
+    #[test]
+    fn renders_negative_coordinates() {
+        let simulation = Simulation::run(&Rope::new(10), &parse_instructions(LARGER_INPUT));
+        let (min, max) = simulation.bounds;
+        assert_eq!((min.x, min.y, max.x, max.y), (-11, -5, 14, 15));
+        let last = simulation.render_frame(simulation.frames.len() - 1);
+        assert_eq!(last.lines().count(), 21);
+        assert_eq!(last.lines().next().unwrap(), "H.........................");
+        assert_eq!(last.lines().nth(9).unwrap(), "9.........................");
+        let cast = simulation.to_cast(50);
+        assert!(cast.starts_with("{\"version\": 2, \"width\": 26, \"height\": 21}\n"));
+        assert_eq!(cast.lines().count(), 1 + 3);
+    }
+
+    #[test]
+    fn rejects_bad_frame_options() {
+        assert_eq!(parse_every("3"), Ok(3));
+        assert!(parse_every("0").is_err());
+        assert!(parse_every("x").is_err());
+        let simulation = Simulation::run(&Rope::new(2), &parse_instructions("R 20000"));
+        assert!(simulation.write_gif(Vec::new(), 1000).is_err());
+        let simulation = Simulation::run(&Rope::new(2), &parse_instructions("R 4\nU 4"));
+        assert!(simulation.write_gif(Vec::new(), 1).is_ok());
+    }
 }
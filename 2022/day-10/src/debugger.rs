@@ -0,0 +1,214 @@
+use crate::instruction::Register;
+use crate::Cpu;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    fn parse(symbol: &str) -> Option<Self> {
+        match symbol {
+            "==" => Some(Comparison::Eq),
+            "!=" => Some(Comparison::Ne),
+            "<" => Some(Comparison::Lt),
+            "<=" => Some(Comparison::Le),
+            ">" => Some(Comparison::Gt),
+            ">=" => Some(Comparison::Ge),
+            _ => None,
+        }
+    }
+    fn holds(&self, left: i32, right: i32) -> bool {
+        match self {
+            Comparison::Eq => left == right,
+            Comparison::Ne => left != right,
+            Comparison::Lt => left < right,
+            Comparison::Le => left <= right,
+            Comparison::Gt => left > right,
+            Comparison::Ge => left >= right,
+        }
+    }
+    fn symbol(&self) -> &'static str {
+        match self {
+            Comparison::Eq => "==",
+            Comparison::Ne => "!=",
+            Comparison::Lt => "<",
+            Comparison::Le => "<=",
+            Comparison::Gt => ">",
+            Comparison::Ge => ">=",
+        }
+    }
+}
+
+enum Breakpoint {
+    Cycle(i32),
+    Register(Register, Comparison, i32),
+}
+
+impl Breakpoint {
+    fn parse(words: &[&str]) -> Option<Self> {
+        match words {
+            ["cycle", cycle] => Some(Breakpoint::Cycle(cycle.parse().ok()?)),
+            [register, comparison, value] => Some(Breakpoint::Register(
+                register.parse().ok()?,
+                Comparison::parse(comparison)?,
+                value.parse().ok()?,
+            )),
+            _ => None,
+        }
+    }
+    fn hit(&self, cpu: &Cpu) -> bool {
+        match self {
+            Breakpoint::Cycle(cycle) => cpu.current_cycle == *cycle,
+            Breakpoint::Register(register, comparison, value) => {
+                comparison.holds(cpu.register(*register), *value)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Breakpoint::Cycle(cycle) => write!(f, "cycle {}", cycle),
+            Breakpoint::Register(register, comparison, value) => {
+                write!(f, "{} {} {}", register, comparison.symbol(), value)
+            }
+        }
+    }
+}
+
+fn write_registers<W: Write>(output: &mut W, cpu: &Cpu) -> io::Result<()> {
+    let registers: Vec<String> = Register::ALL
+        .iter()
+        .map(|register| format!("{}={}", register, cpu.register(*register)))
+        .collect();
+    writeln!(
+        output,
+        "cycle {} pc {} {}",
+        cpu.current_cycle,
+        cpu.pc,
+        registers.join(" ")
+    )
+}
+
+pub fn run<R: BufRead, W: Write>(mut cpu: Cpu, input: R, mut output: W) -> io::Result<()> {
+    let mut breakpoints: Vec<Breakpoint> = Vec::new();
+    write!(output, "(debug) ")?;
+    output.flush()?;
+    for line in input.lines() {
+        let line = line?;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["break", condition @ ..] => match Breakpoint::parse(condition) {
+                Some(breakpoint) => {
+                    breakpoints.push(breakpoint);
+                    writeln!(
+                        output,
+                        "breakpoint {}: {}",
+                        breakpoints.len(),
+                        breakpoints.last().unwrap()
+                    )?;
+                }
+                None => writeln!(output, "invalid breakpoint `{}`", condition.join(" "))?,
+            },
+            ["delete", index] => match index.parse::<usize>() {
+                Ok(index) if (1..=breakpoints.len()).contains(&index) => {
+                    breakpoints.remove(index - 1);
+                }
+                _ => writeln!(output, "no breakpoint {}", index)?,
+            },
+            ["breakpoints"] => {
+                for (index, breakpoint) in breakpoints.iter().enumerate() {
+                    writeln!(output, "breakpoint {}: {}", index + 1, breakpoint)?;
+                }
+            }
+            ["step"] | ["step", _] => {
+                let count = words.get(1).map_or(Some(1), |count| count.parse().ok());
+                for _ in 0..count.unwrap_or(0) {
+                    match cpu.tick() {
+                        Ok(Some(report)) => writeln!(output, "{}", report)?,
+                        Ok(None) => break,
+                        Err(err) => {
+                            writeln!(output, "{}", err)?;
+                            break;
+                        }
+                    }
+                }
+            }
+            ["continue"] => loop {
+                let report = match cpu.tick() {
+                    Ok(Some(report)) => report,
+                    Ok(None) => break,
+                    Err(err) => {
+                        writeln!(output, "{}", err)?;
+                        break;
+                    }
+                };
+                if let Some(index) = breakpoints.iter().position(|bp| bp.hit(&cpu)) {
+                    writeln!(
+                        output,
+                        "hit breakpoint {}: {}",
+                        index + 1,
+                        breakpoints[index]
+                    )?;
+                    writeln!(output, "{}", report)?;
+                    break;
+                }
+            },
+            ["regs"] => write_registers(&mut output, &cpu)?,
+            ["screen"] => writeln!(output, "{}", cpu.display_screen())?,
+            ["quit"] => return Ok(()),
+            [] => {}
+            _ => writeln!(output, "unknown command `{}`", line.trim())?,
+        }
+        if cpu.halted() {
+            writeln!(output, "program halted after {} cycles", cpu.current_cycle)?;
+        }
+        write!(output, "(debug) ")?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load_program;
+
+    #[test]
+    fn breakpoints() {
+        let cpu = Cpu::new(load_program("addx 3\naddx -5\nnoop\naddx 2"));
+        let script = "break cycle 3\ncontinue\nregs\nbreak x < 0\ncontinue\nstep\nstep 9\nquit";
+        let mut output = Vec::new();
+        run(cpu, script.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "(debug) breakpoint 1: cycle 3");
+        assert_eq!(lines[1], "(debug) hit breakpoint 1: cycle 3");
+        assert!(lines[2].starts_with("cycle    3 | addx -5      | X    4"));
+        assert_eq!(lines[3], "(debug) cycle 3 pc 1 x=4 y=0 z=0 w=0");
+        assert_eq!(lines[5], "(debug) hit breakpoint 2: x < 0");
+        assert!(lines[6].starts_with("cycle    4 | addx -5      | X    4"));
+        assert!(lines[7].starts_with("(debug) cycle    5 | noop         | X   -1"));
+        assert_eq!(lines[10], "program halted after 7 cycles");
+    }
+
+    #[test]
+    fn continue_stops_runaway_programs() {
+        let mut cpu = Cpu::new(load_program("addx 1\njmp -1"));
+        cpu.max_cycles = 50;
+        let mut output = Vec::new();
+        run(cpu, "continue\nregs\nquit".as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "(debug) program did not halt within 50 cycles");
+        assert!(lines[1].starts_with("(debug) cycle 50 pc "));
+    }
+}
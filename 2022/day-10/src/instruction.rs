@@ -0,0 +1,343 @@
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Register {
+    X,
+    Y,
+    Z,
+    W,
+}
+
+impl Register {
+    pub const ALL: [Register; 4] = [Register::X, Register::Y, Register::Z, Register::W];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operand {
+    Register(Register),
+    Immediate(i32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Instruction {
+    Noop,
+    Addx(i32),
+    Add(Register, Operand),
+    Mul(Register, Operand),
+    Mov(Register, Operand),
+    Jmp(i32),
+    Jnz(Operand, i32),
+}
+
+pub const INSTRUCTION_TABLE: [(&str, u32); 7] = [
+    ("noop", 1),
+    ("addx", 2),
+    ("add", 2),
+    ("mul", 3),
+    ("mov", 1),
+    ("jmp", 1),
+    ("jnz", 2),
+];
+
+impl Instruction {
+    fn opcode(&self) -> u8 {
+        match self {
+            Instruction::Noop => 0,
+            Instruction::Addx(_) => 1,
+            Instruction::Add(_, _) => 2,
+            Instruction::Mul(_, _) => 3,
+            Instruction::Mov(_, _) => 4,
+            Instruction::Jmp(_) => 5,
+            Instruction::Jnz(_, _) => 6,
+        }
+    }
+    pub fn mnemonic(&self) -> &'static str {
+        INSTRUCTION_TABLE[self.opcode() as usize].0
+    }
+    pub fn cycles(&self) -> u32 {
+        INSTRUCTION_TABLE[self.opcode() as usize].1
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+    MissingMnemonic,
+    UnknownMnemonic(String),
+    MissingOperand(String),
+    UnexpectedOperand(String),
+    InvalidRegister(String),
+    InvalidNumber(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::MissingMnemonic => write!(f, "missing mnemonic"),
+            ParseError::UnknownMnemonic(word) => write!(f, "unknown mnemonic `{}`", word),
+            ParseError::MissingOperand(mnemonic) => {
+                write!(f, "`{}` is missing an operand", mnemonic)
+            }
+            ParseError::UnexpectedOperand(word) => write!(f, "unexpected operand `{}`", word),
+            ParseError::InvalidRegister(word) => write!(f, "invalid register `{}`", word),
+            ParseError::InvalidNumber(word) => write!(f, "invalid number `{}`", word),
+        }
+    }
+}
+
+fn parse_number(word: &str) -> Result<i32, ParseError> {
+    word.parse()
+        .map_err(|_| ParseError::InvalidNumber(word.to_string()))
+}
+
+impl FromStr for Register {
+    type Err = ParseError;
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "x" => Ok(Register::X),
+            "y" => Ok(Register::Y),
+            "z" => Ok(Register::Z),
+            "w" => Ok(Register::W),
+            _ => Err(ParseError::InvalidRegister(input.to_string())),
+        }
+    }
+}
+
+impl FromStr for Operand {
+    type Err = ParseError;
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.parse() {
+            Ok(register) => Ok(Operand::Register(register)),
+            Err(_) => parse_number(input).map(Operand::Immediate),
+        }
+    }
+}
+
+impl FromStr for Instruction {
+    type Err = ParseError;
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let mut words = line.split_whitespace();
+        let mnemonic = words.next().ok_or(ParseError::MissingMnemonic)?;
+        let mut operand = || {
+            words
+                .next()
+                .ok_or_else(|| ParseError::MissingOperand(mnemonic.to_string()))
+        };
+        let instruction = match mnemonic {
+            "noop" => Instruction::Noop,
+            "addx" => Instruction::Addx(parse_number(operand()?)?),
+            "add" => Instruction::Add(operand()?.parse()?, operand()?.parse()?),
+            "mul" => Instruction::Mul(operand()?.parse()?, operand()?.parse()?),
+            "mov" => Instruction::Mov(operand()?.parse()?, operand()?.parse()?),
+            "jmp" => Instruction::Jmp(parse_number(operand()?)?),
+            "jnz" => Instruction::Jnz(operand()?.parse()?, parse_number(operand()?)?),
+            _ => return Err(ParseError::UnknownMnemonic(mnemonic.to_string())),
+        };
+        match words.next() {
+            Some(word) => Err(ParseError::UnexpectedOperand(word.to_string())),
+            None => Ok(instruction),
+        }
+    }
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Register::X => "x",
+            Register::Y => "y",
+            Register::Z => "z",
+            Register::W => "w",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operand::Register(register) => write!(f, "{}", register),
+            Operand::Immediate(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.mnemonic())?;
+        match self {
+            Instruction::Noop => Ok(()),
+            Instruction::Addx(value) | Instruction::Jmp(value) => write!(f, " {}", value),
+            Instruction::Add(register, operand)
+            | Instruction::Mul(register, operand)
+            | Instruction::Mov(register, operand) => write!(f, " {} {}", register, operand),
+            Instruction::Jnz(operand, offset) => write!(f, " {} {}", operand, offset),
+        }
+    }
+}
+
+pub fn parse_program(input: &str) -> Result<Vec<Instruction>, (usize, ParseError)> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| line.parse().map_err(|err| (index + 1, err)))
+        .collect()
+}
+
+pub fn format_program(program: &[Instruction]) -> String {
+    program
+        .iter()
+        .map(|instruction| instruction.to_string())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[derive(Debug, PartialEq)]
+pub enum DecodeError {
+    Truncated,
+    UnknownOpcode(u8),
+    InvalidRegister(u8),
+    InvalidOperandTag(u8),
+}
+
+fn encode_register(bytes: &mut Vec<u8>, register: &Register) {
+    bytes.push(*register as u8);
+}
+
+fn encode_number(bytes: &mut Vec<u8>, value: &i32) {
+    bytes.extend(value.to_le_bytes());
+}
+
+fn encode_operand(bytes: &mut Vec<u8>, operand: &Operand) {
+    match operand {
+        Operand::Register(register) => {
+            bytes.push(0);
+            encode_register(bytes, register);
+        }
+        Operand::Immediate(value) => {
+            bytes.push(1);
+            encode_number(bytes, value);
+        }
+    }
+}
+
+pub fn assemble(program: &[Instruction]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for instruction in program {
+        bytes.push(instruction.opcode());
+        match instruction {
+            Instruction::Noop => {}
+            Instruction::Addx(value) | Instruction::Jmp(value) => encode_number(&mut bytes, value),
+            Instruction::Add(register, operand)
+            | Instruction::Mul(register, operand)
+            | Instruction::Mov(register, operand) => {
+                encode_register(&mut bytes, register);
+                encode_operand(&mut bytes, operand);
+            }
+            Instruction::Jnz(operand, offset) => {
+                encode_operand(&mut bytes, operand);
+                encode_number(&mut bytes, offset);
+            }
+        }
+    }
+    bytes
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+}
+
+impl Decoder<'_> {
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        let (&byte, rest) = self.bytes.split_first().ok_or(DecodeError::Truncated)?;
+        self.bytes = rest;
+        Ok(byte)
+    }
+    fn number(&mut self) -> Result<i32, DecodeError> {
+        let mut word = [0; 4];
+        for byte in word.iter_mut() {
+            *byte = self.byte()?;
+        }
+        Ok(i32::from_le_bytes(word))
+    }
+    fn register(&mut self) -> Result<Register, DecodeError> {
+        let index = self.byte()?;
+        Register::ALL
+            .get(index as usize)
+            .copied()
+            .ok_or(DecodeError::InvalidRegister(index))
+    }
+    fn operand(&mut self) -> Result<Operand, DecodeError> {
+        match self.byte()? {
+            0 => Ok(Operand::Register(self.register()?)),
+            1 => Ok(Operand::Immediate(self.number()?)),
+            tag => Err(DecodeError::InvalidOperandTag(tag)),
+        }
+    }
+    fn instruction(&mut self) -> Result<Instruction, DecodeError> {
+        Ok(match self.byte()? {
+            0 => Instruction::Noop,
+            1 => Instruction::Addx(self.number()?),
+            2 => Instruction::Add(self.register()?, self.operand()?),
+            3 => Instruction::Mul(self.register()?, self.operand()?),
+            4 => Instruction::Mov(self.register()?, self.operand()?),
+            5 => Instruction::Jmp(self.number()?),
+            6 => Instruction::Jnz(self.operand()?, self.number()?),
+            opcode => return Err(DecodeError::UnknownOpcode(opcode)),
+        })
+    }
+}
+
+pub fn disassemble(bytes: &[u8]) -> Result<Vec<Instruction>, DecodeError> {
+    let mut decoder = Decoder { bytes };
+    let mut program = Vec::new();
+    while !decoder.bytes.is_empty() {
+        program.push(decoder.instruction()?);
+    }
+    Ok(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    const PROGRAM: &str = "noop
+addx -11
+mov y 3
+add x y
+mul z -2
+jnz y -2
+jmp 4";
+
+    #[test]
+    fn round_trip() {
+        let program = parse_program(PROGRAM).unwrap();
+        assert_eq!(
+            program[2],
+            Instruction::Mov(Register::Y, Operand::Immediate(3))
+        );
+        assert_eq!(format_program(&program), PROGRAM);
+        assert_eq!(disassemble(&assemble(&program)), Ok(program));
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        assert_eq!(
+            parse_program("noop\nnoopp"),
+            Err((2, ParseError::UnknownMnemonic("noopp".to_string())))
+        );
+        assert_eq!(
+            "addx".parse::<Instruction>(),
+            Err(ParseError::MissingOperand("addx".to_string()))
+        );
+        assert_eq!(
+            "noop 3".parse::<Instruction>(),
+            Err(ParseError::UnexpectedOperand("3".to_string()))
+        );
+        assert_eq!(
+            "add q 1".parse::<Instruction>(),
+            Err(ParseError::InvalidRegister("q".to_string()))
+        );
+        assert_eq!(disassemble(&[1, 2]), Err(DecodeError::Truncated));
+        assert_eq!(disassemble(&[9]), Err(DecodeError::UnknownOpcode(9)));
+    }
+}
@@ -1,84 +1,424 @@
+use instruction::{
+    assemble, disassemble, format_program, parse_program, Instruction, Operand, Register,
+};
 use itertools::Itertools;
+use std::env;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
+
+mod debugger;
+mod instruction;
+mod ocr;
+
 fn main() {
     let input = include_str!("input");
     println!("{}", part_one(input));
     println!();
     println!("{}", part_two(input));
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    let setting =
+        |name, default| option_value(&args, name).map_or(default, |value| value.parse().unwrap());
+    let crt = match Crt::new(
+        setting("--width", 40),
+        setting("--height", 6),
+        setting("--sprite", 3),
+        setting("--first-sample", 20),
+        setting("--sample-every", 40),
+    ) {
+        Ok(crt) => crt,
+        Err(err) => {
+            eprintln!("{}", err);
+            return;
+        }
+    };
+    let max_cycles = setting("--max-cycles", MAX_CYCLES);
+    let scale = match option_value(&args, "--scale").map_or(Ok(8), |value| value.parse()) {
+        Ok(scale) if scale > 0 => scale,
+        _ => {
+            eprintln!("PNG scale must be a positive integer");
+            return;
+        }
+    };
+    let cpu = || {
+        let mut cpu = Cpu::with_crt(load_program(input), crt);
+        cpu.max_cycles = max_cycles;
+        cpu
+    };
+    let mut screen = cpu();
+    if let Err(err) = screen.run() {
+        println!();
+        println!("{}", err);
+    }
+    if crt != Crt::default() {
+        println!();
+        println!("{}", screen.total_signal_strength);
+        println!();
+        println!("{}", screen.display_screen());
+    }
+    if let Some(path) = option_value(&args, "--pbm") {
+        fs::write(path, screen.to_pbm()).unwrap();
+    }
+    if let Some(path) = option_value(&args, "--png") {
+        screen
+            .write_png(File::create(path).unwrap(), scale)
+            .unwrap();
+    }
+    if let Some(path) = option_value(&args, "--assemble") {
+        fs::write(path, assemble(&load_program(input))).unwrap();
+    }
+    if let Some(path) = option_value(&args, "--disassemble") {
+        println!();
+        println!(
+            "{}",
+            format_program(&disassemble(&fs::read(path).unwrap()).unwrap())
+        );
+    }
+    if args.iter().any(|arg| arg == "--trace") {
+        let mut cpu = cpu();
+        println!();
+        loop {
+            match cpu.tick() {
+                Ok(Some(report)) => println!("{}", report),
+                Ok(None) => break,
+                Err(err) => {
+                    println!("{}", err);
+                    break;
+                }
+            }
+        }
+    }
+    if args.iter().any(|arg| arg == "--debug") {
+        debugger::run(cpu(), io::stdin().lock(), io::stdout()).unwrap();
+    }
+}
+
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|pos| args.get(pos + 1))
+        .map(|value| value.as_str())
 }
 
-#[derive(Debug)]
-enum Instruction {
-    Noop,
-    Addx(i32),
+fn load_program(input: &str) -> Vec<Instruction> {
+    parse_program(input).unwrap_or_else(|(line, err)| panic!("line {}: {}", line, err))
 }
 
-fn parse_instruction(line: &str) -> Instruction {
-    let mut split = line.split(" ");
-    let _ = split.next().unwrap();
-    match split.next() {
-        Some(secs) => Instruction::Addx(secs.parse::<i32>().unwrap()),
-        _ => Instruction::Noop,
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Crt {
+    width: i32,
+    height: i32,
+    sprite_width: i32,
+    first_sample: i32,
+    sample_every: i32,
+}
+
+impl Default for Crt {
+    fn default() -> Self {
+        Self {
+            width: 40,
+            height: 6,
+            sprite_width: 3,
+            first_sample: 20,
+            sample_every: 40,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum CrtError {
+    NotPositive(&'static str, i32),
+    TooManyPixels(i32, i32),
+}
+
+impl fmt::Display for CrtError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CrtError::NotPositive(setting, value) => {
+                write!(f, "CRT {} must be positive, got {}", setting, value)
+            }
+            CrtError::TooManyPixels(width, height) => {
+                write!(f, "a {}x{} CRT has too many pixels", width, height)
+            }
+        }
+    }
+}
+
+impl Crt {
+    fn new(
+        width: i32,
+        height: i32,
+        sprite_width: i32,
+        first_sample: i32,
+        sample_every: i32,
+    ) -> Result<Self, CrtError> {
+        for (setting, value) in [
+            ("width", width),
+            ("height", height),
+            ("sprite width", sprite_width),
+            ("sample interval", sample_every),
+        ] {
+            if value <= 0 {
+                return Err(CrtError::NotPositive(setting, value));
+            }
+        }
+        if width.checked_mul(height).is_none() {
+            return Err(CrtError::TooManyPixels(width, height));
+        }
+        Ok(Self {
+            width,
+            height,
+            sprite_width,
+            first_sample,
+            sample_every,
+        })
+    }
+    fn sprite(&self, x: i32) -> (i32, i32) {
+        let left = x.saturating_sub((self.sprite_width - 1) / 2);
+        (left, left.saturating_add(self.sprite_width - 1))
+    }
+    fn samples(&self, cycle: i32) -> bool {
+        cycle >= self.first_sample && (cycle - self.first_sample) % self.sample_every == 0
+    }
+    fn pixel_count(&self) -> usize {
+        (self.width * self.height) as usize
+    }
+}
+
+struct CycleReport {
+    cycle: i32,
+    instruction: Instruction,
+    x: i32,
+    sprite: (i32, i32),
+    pixel: char,
+    signal: i32,
+}
+
+impl fmt::Display for CycleReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "cycle {:>4} | {:<12} | X {:>4} | sprite {:>3}..{:<3} | pixel {} | signal {}",
+            self.cycle,
+            self.instruction.to_string(),
+            self.x,
+            self.sprite.0,
+            self.sprite.1,
+            self.pixel,
+            self.signal
+        )
+    }
+}
+
+const MAX_CYCLES: i32 = 1_000_000;
+
+#[derive(Debug, PartialEq)]
+enum CpuError {
+    CycleLimit(i32),
+    Overflow(i32, Instruction),
+}
+
+impl fmt::Display for CpuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CpuError::CycleLimit(limit) => {
+                write!(f, "program did not halt within {} cycles", limit)
+            }
+            CpuError::Overflow(cycle, instruction) => {
+                write!(
+                    f,
+                    "cycle {}: `{}` overflowed a register",
+                    cycle, instruction
+                )
+            }
+        }
     }
 }
 
 struct Cpu {
-    register_value: i32,
+    program: Vec<Instruction>,
+    registers: [i32; 4],
+    pc: usize,
+    elapsed: u32,
     total_signal_strength: i32,
     current_cycle: i32,
-    pixels: String,
+    max_cycles: i32,
+    crt: Crt,
+    pixels: Vec<char>,
 }
 
 impl Cpu {
-    fn new() -> Self {
+    fn new(program: Vec<Instruction>) -> Self {
+        Self::with_crt(program, Crt::default())
+    }
+    fn with_crt(program: Vec<Instruction>, crt: Crt) -> Self {
         Self {
-            register_value: 1,
+            program,
+            registers: [1, 0, 0, 0],
+            pc: 0,
+            elapsed: 0,
             total_signal_strength: 0,
             current_cycle: 0,
-            pixels: "".to_string(),
+            max_cycles: MAX_CYCLES,
+            crt,
+            pixels: vec!['.'; crt.pixel_count()],
+        }
+    }
+    fn register(&self, register: Register) -> i32 {
+        self.registers[register as usize]
+    }
+    fn value(&self, operand: &Operand) -> i32 {
+        match operand {
+            Operand::Register(register) => self.register(*register),
+            Operand::Immediate(value) => *value,
         }
     }
-    fn advance_clock(&mut self) {
+    fn halted(&self) -> bool {
+        self.pc >= self.program.len()
+    }
+    fn advance_clock(&mut self, instruction: Instruction) -> CycleReport {
         self.current_cycle += 1;
-        if (self.register_value - (self.current_cycle - 1) % 40).abs() < 2 {
-            self.pixels += "#";
+        let register_value = self.register(Register::X);
+        let position = (self.current_cycle - 1) as usize % self.crt.pixel_count();
+        let sprite = self.crt.sprite(register_value);
+        let column = position as i32 % self.crt.width;
+        let pixel = if (sprite.0..=sprite.1).contains(&column) {
+            '#'
         } else {
-            self.pixels += ".";
+            '.'
+        };
+        self.pixels[position] = pixel;
+        let mut signal = 0;
+        if self.crt.samples(self.current_cycle) {
+            signal = self.current_cycle.saturating_mul(register_value);
+            self.total_signal_strength = self.total_signal_strength.saturating_add(signal);
         }
-        if self.current_cycle == 20 || (self.current_cycle + 20) % 40 == 0 {
-            self.total_signal_strength += self.current_cycle * self.register_value;
+        CycleReport {
+            cycle: self.current_cycle,
+            instruction,
+            x: register_value,
+            sprite,
+            pixel,
+            signal,
         }
     }
-    fn update_register(&mut self, val: &i32) {
-        self.register_value += val;
+    fn jump(&mut self, offset: i32) {
+        self.pc = self
+            .pc
+            .checked_add_signed(offset as isize)
+            .unwrap_or(usize::MAX);
+    }
+    fn store(
+        &mut self,
+        register: Register,
+        value: Option<i32>,
+        instr: &Instruction,
+    ) -> Result<(), CpuError> {
+        let value = value.ok_or(CpuError::Overflow(self.current_cycle, *instr))?;
+        self.registers[register as usize] = value;
+        Ok(())
     }
-    fn process_instruction(&mut self, instr: &Instruction) {
+    fn execute(&mut self, instr: &Instruction) -> Result<(), CpuError> {
         match instr {
-            Instruction::Noop => {
-                self.advance_clock();
-            }
+            Instruction::Noop => {}
             Instruction::Addx(val) => {
-                self.advance_clock();
-                self.advance_clock();
-                self.update_register(val);
+                let sum = self.register(Register::X).checked_add(*val);
+                self.store(Register::X, sum, instr)?
+            }
+            Instruction::Add(register, operand) => {
+                let sum = self.register(*register).checked_add(self.value(operand));
+                self.store(*register, sum, instr)?
+            }
+            Instruction::Mul(register, operand) => {
+                let product = self.register(*register).checked_mul(self.value(operand));
+                self.store(*register, product, instr)?
             }
+            Instruction::Mov(register, operand) => {
+                self.registers[*register as usize] = self.value(operand)
+            }
+            Instruction::Jmp(offset) => {
+                self.jump(*offset);
+                return Ok(());
+            }
+            Instruction::Jnz(operand, offset) => {
+                if self.value(operand) != 0 {
+                    self.jump(*offset);
+                    return Ok(());
+                }
+            }
+        }
+        self.pc += 1;
+        Ok(())
+    }
+    fn tick(&mut self) -> Result<Option<CycleReport>, CpuError> {
+        if self.halted() {
+            return Ok(None);
+        }
+        if self.current_cycle >= self.max_cycles {
+            return Err(CpuError::CycleLimit(self.max_cycles));
+        }
+        let instr = self.program[self.pc];
+        let report = self.advance_clock(instr);
+        self.elapsed += 1;
+        if self.elapsed == instr.cycles() {
+            self.elapsed = 0;
+            self.execute(&instr)?;
         }
+        Ok(Some(report))
+    }
+    fn run(&mut self) -> Result<(), CpuError> {
+        while self.tick()?.is_some() {}
+        Ok(())
     }
     fn display_screen(&self) -> String {
         self.pixels
-            .chars()
-            .chunks(40)
-            .into_iter()
-            .map(|row| row.collect::<String>())
+            .chunks(self.crt.width as usize)
+            .map(|row| row.iter().collect::<String>())
             .join("\n")
     }
+    fn to_pbm(&self) -> String {
+        let mut image = format!("P1\n{} {}\n", self.crt.width, self.crt.height);
+        for row in self.pixels.chunks(self.crt.width as usize) {
+            image += &row
+                .iter()
+                .map(|&pixel| if pixel == '#' { "1" } else { "0" })
+                .join(" ");
+            image += "\n";
+        }
+        image
+    }
+    fn write_png<W: Write>(&self, writer: W, scale: u32) -> Result<(), png::EncodingError> {
+        let too_large = || io::Error::new(io::ErrorKind::InvalidInput, "PNG is too large");
+        let width = (self.crt.width as u32)
+            .checked_mul(scale)
+            .ok_or_else(too_large)?;
+        let height = (self.crt.height as u32)
+            .checked_mul(scale)
+            .ok_or_else(too_large)?;
+        let mut encoder = png::Encoder::new(writer, width, height);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut data = Vec::with_capacity((width * height) as usize);
+        for row in self.pixels.chunks(self.crt.width as usize) {
+            let line: Vec<u8> = row
+                .iter()
+                .flat_map(|&pixel| {
+                    let shade = if pixel == '#' { 0xff } else { 0x00 };
+                    std::iter::repeat_n(shade, scale as usize)
+                })
+                .collect();
+            for _ in 0..scale {
+                data.extend(&line);
+            }
+        }
+        encoder.write_header()?.write_image_data(&data)
+    }
 }
 
 fn simulate_program(input: &str) -> Cpu {
-    let mut cpu = Cpu::new();
-    for line in input.lines() {
-        let current_instr = parse_instruction(line);
-        cpu.process_instruction(&current_instr);
-    }
+    let mut cpu = Cpu::new(load_program(input));
+    cpu.run().unwrap_or_else(|err| panic!("{}", err));
     cpu
 }
 
@@ -87,7 +427,7 @@ fn part_one(input: &str) -> i32 {
 }
 
 fn part_two(input: &str) -> String {
-    simulate_program(input).display_screen()
+    ocr::recognize(&simulate_program(input).display_screen())
 }
 
 #[cfg(test)]
@@ -245,10 +585,102 @@ noop";
         assert_eq!(part_one(INPUT), 13140);
     }
 
+    #[test]
+    fn extended_instructions() {
+        let cpu =
+            simulate_program("mov y 3\nmul y 2\nadd x y\nadd y -1\njnz y -2\njmp 5\naddx 100");
+        assert_eq!(cpu.register(Register::X), 1 + 6 + 5 + 4 + 3 + 2 + 1);
+        assert_eq!(cpu.register(Register::Y), 0);
+        assert_eq!(cpu.current_cycle, 1 + 3 + 6 * (2 + 2 + 2) + 1);
+    }
+
+    #[test]
+    fn trace() {
+        let mut cpu = Cpu::new(load_program(INPUT));
+        let reports: Vec<CycleReport> = std::iter::from_fn(|| cpu.tick().unwrap()).collect();
+        assert_eq!(reports.len(), 240);
+        assert_eq!(reports[19].signal, 420);
+        assert_eq!(
+            reports[2].to_string(),
+            "cycle    3 | addx -11     | X   16 | sprite  15..17  | pixel . | signal 0"
+        );
+    }
+
+    #[test]
+    fn custom_geometry() {
+        let crt = Crt::new(8, 2, 5, 3, 5).unwrap();
+        let mut cpu = Cpu::with_crt(load_program("addx 4\nnoop\naddx -4\nnoop\nnoop\nnoop"), crt);
+        cpu.run().unwrap();
+        assert_eq!(cpu.total_signal_strength, 3 * 5 + 8);
+        assert_eq!(cpu.display_screen(), "##.##...\n........");
+        assert_eq!(cpu.to_pbm(), "P1\n8 2\n1 1 0 1 1 0 0 0\n0 0 0 0 0 0 0 0\n");
+        let mut png = Vec::new();
+        cpu.write_png(&mut png, 2).unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+        assert!(cpu.write_png(&mut Vec::new(), u32::MAX).is_err());
+    }
+
+    #[test]
+    fn rejects_degenerate_geometry() {
+        assert_eq!(
+            Crt::new(0, 6, 3, 20, 40),
+            Err(CrtError::NotPositive("width", 0))
+        );
+        assert_eq!(
+            Crt::new(40, -1, 3, 20, 40),
+            Err(CrtError::NotPositive("height", -1))
+        );
+        assert_eq!(
+            Crt::new(40, 6, i32::MIN, 20, 40),
+            Err(CrtError::NotPositive("sprite width", i32::MIN))
+        );
+        assert_eq!(
+            Crt::new(40, 6, 3, 20, 0).unwrap_err().to_string(),
+            "CRT sample interval must be positive, got 0"
+        );
+        assert_eq!(
+            Crt::new(65536, 65536, 3, 20, 40),
+            Err(CrtError::TooManyPixels(65536, 65536))
+        );
+        assert_eq!(Crt::new(40, 6, 3, 20, 40), Ok(Crt::default()));
+    }
+
+    #[test]
+    fn runaway_programs() {
+        let mut cpu = Cpu::new(load_program("noop\njmp 0"));
+        cpu.max_cycles = 100;
+        assert_eq!(cpu.run(), Err(CpuError::CycleLimit(100)));
+        assert_eq!(cpu.current_cycle, 100);
+        let mut cpu = Cpu::new(load_program("mov y 2147483647\nmul y 2\nnoop"));
+        assert_eq!(
+            cpu.run(),
+            Err(CpuError::Overflow(
+                4,
+                Instruction::Mul(Register::Y, Operand::Immediate(2))
+            ))
+        );
+        let mut cpu = Cpu::new(load_program(
+            "mov x 2147483647\naddx 0\nnoop\n".repeat(10).as_str(),
+        ));
+        cpu.run().unwrap();
+        assert_eq!(cpu.total_signal_strength, i32::MAX);
+        let mut cpu = Cpu::new(load_program("mov x -2147483648\nadd x -1"));
+        assert_eq!(
+            cpu.run().unwrap_err().to_string(),
+            "cycle 3: `add x -1` overflowed a register"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "line 2: unknown mnemonic `nop`")]
+    fn rejects_unknown_mnemonics() {
+        simulate_program("noop\nnop");
+    }
+
     #[test]
     fn part_two_example() {
         assert_eq!(
-            part_two(INPUT),
+            simulate_program(INPUT).display_screen(),
             "##..##..##..##..##..##..##..##..##..##..
 ###...###...###...###...###...###...###.
 ####....####....####....####....####....
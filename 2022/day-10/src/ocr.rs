@@ -0,0 +1,277 @@
+const SMALL_FONT: [(char, [&str; 6]); 18] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Y', ["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#.."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+const LARGE_FONT: [(char, [&str; 10]); 15] = [
+    (
+        'A',
+        [
+            "..##..", ".#..#.", "#....#", "#....#", "#....#", "######", "#....#", "#....#",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'B',
+        [
+            "#####.", "#....#", "#....#", "#....#", "#####.", "#....#", "#....#", "#....#",
+            "#....#", "#####.",
+        ],
+    ),
+    (
+        'C',
+        [
+            ".####.", "#....#", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....",
+            "#....#", ".####.",
+        ],
+    ),
+    (
+        'E',
+        [
+            "######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....",
+            "#.....", "######",
+        ],
+    ),
+    (
+        'F',
+        [
+            "######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....",
+            "#.....", "#.....",
+        ],
+    ),
+    (
+        'G',
+        [
+            ".####.", "#....#", "#.....", "#.....", "#.....", "#..###", "#....#", "#....#",
+            "#...##", ".###.#",
+        ],
+    ),
+    (
+        'H',
+        [
+            "#....#", "#....#", "#....#", "#....#", "######", "#....#", "#....#", "#....#",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'J',
+        [
+            "...###", "....#.", "....#.", "....#.", "....#.", "....#.", "....#.", "#...#.",
+            "#...#.", ".###..",
+        ],
+    ),
+    (
+        'K',
+        [
+            "#....#", "#...#.", "#..#..", "#.#...", "##....", "##....", "#.#...", "#..#..",
+            "#...#.", "#....#",
+        ],
+    ),
+    (
+        'L',
+        [
+            "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....",
+            "#.....", "######",
+        ],
+    ),
+    (
+        'N',
+        [
+            "#....#", "##...#", "##...#", "#.#..#", "#.#..#", "#..#.#", "#..#.#", "#...##",
+            "#...##", "#....#",
+        ],
+    ),
+    (
+        'P',
+        [
+            "#####.", "#....#", "#....#", "#....#", "#####.", "#.....", "#.....", "#.....",
+            "#.....", "#.....",
+        ],
+    ),
+    (
+        'R',
+        [
+            "#####.", "#....#", "#....#", "#....#", "#####.", "#..#..", "#...#.", "#...#.",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'X',
+        [
+            "#....#", "#....#", ".#..#.", ".#..#.", "..##..", "..##..", ".#..#.", ".#..#.",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'Z',
+        [
+            "######", ".....#", ".....#", "....#.", "...#..", "..#...", ".#....", "#.....",
+            "#.....", "######",
+        ],
+    ),
+];
+
+type Bitmap = Vec<Vec<bool>>;
+
+fn to_bitmap(rows: &[&str]) -> Bitmap {
+    rows.iter()
+        .map(|row| row.chars().map(|c| c == '#').collect())
+        .collect()
+}
+
+fn glyph_columns(bitmap: &Bitmap) -> Vec<(usize, usize)> {
+    let width = bitmap.iter().map(|row| row.len()).max().unwrap_or(0);
+    let lit = |col: usize| bitmap.iter().any(|row| row.get(col) == Some(&true));
+    let mut glyphs = Vec::new();
+    let mut start = None;
+    for col in 0..=width {
+        match (start, col < width && lit(col)) {
+            (None, true) => start = Some(col),
+            (Some(first), false) => {
+                glyphs.push((first, col));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    glyphs
+}
+
+fn glyph_cells(bitmap: &Bitmap, widest: usize) -> Vec<(usize, usize)> {
+    glyph_columns(bitmap)
+        .into_iter()
+        .flat_map(|(first, last)| {
+            if last - first <= widest {
+                return vec![(first, last)];
+            }
+            (first..last)
+                .step_by(widest)
+                .flat_map(|start| {
+                    let cell = crop(bitmap, (start, last.min(start + widest)));
+                    glyph_columns(&cell)
+                        .into_iter()
+                        .map(move |(first, last)| (start + first, start + last))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn crop(bitmap: &Bitmap, (first, last): (usize, usize)) -> Bitmap {
+    bitmap
+        .iter()
+        .map(|row| {
+            (first..last)
+                .map(|col| row.get(col) == Some(&true))
+                .collect()
+        })
+        .collect()
+}
+
+fn trimmed(rows: &[&str]) -> Bitmap {
+    let bitmap = to_bitmap(rows);
+    let columns = glyph_columns(&bitmap);
+    match (columns.first(), columns.last()) {
+        (Some((first, _)), Some((_, last))) => crop(&bitmap, (*first, *last)),
+        _ => bitmap,
+    }
+}
+
+fn font_for(height: usize) -> Vec<(char, Bitmap)> {
+    match height {
+        6 => SMALL_FONT
+            .iter()
+            .map(|(letter, rows)| (*letter, trimmed(rows)))
+            .collect(),
+        10 => LARGE_FONT
+            .iter()
+            .map(|(letter, rows)| (*letter, trimmed(rows)))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+pub fn recognize(image: &str) -> String {
+    let rows: Vec<&str> = image.lines().collect();
+    let bitmap = to_bitmap(&rows);
+    let font = font_for(bitmap.len());
+    let widest = font.iter().map(|(_, template)| template[0].len()).max();
+    glyph_cells(&bitmap, widest.unwrap_or(usize::MAX))
+        .into_iter()
+        .map(|columns| {
+            let glyph = crop(&bitmap, columns);
+            font.iter()
+                .find(|(_, template)| *template == glyph)
+                .map_or('?', |(letter, _)| *letter)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_font() {
+        let image = "####.#..#.###..####.###....##..##..#....
+#....#..#.#..#....#.#..#....#.#..#.#....
+###..####.#..#...#..#..#....#.#....#....
+#....#..#.###...#...###.....#.#.##.#....
+#....#..#.#....#....#....#..#.#..#.#....
+####.#..#.#....####.#.....##...###.####.";
+        assert_eq!(recognize(image), "EHPZPJGL");
+    }
+
+    #[test]
+    fn touching_glyphs() {
+        let glyphs = [
+            SMALL_FONT[16].1,
+            SMALL_FONT[0].1,
+            SMALL_FONT[16].1,
+            SMALL_FONT[7].1,
+        ];
+        let image = (0..6)
+            .map(|row| {
+                glyphs
+                    .iter()
+                    .map(|glyph| format!("{:.<5}", glyph[row]))
+                    .collect::<String>()
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        assert_eq!(recognize(&image), "YAYI");
+    }
+
+    #[test]
+    fn large_font() {
+        let glyphs = [LARGE_FONT[14].1, LARGE_FONT[10].1, LARGE_FONT[13].1];
+        let image = (0..10)
+            .map(|row| {
+                glyphs
+                    .iter()
+                    .map(|glyph| glyph[row])
+                    .collect::<Vec<_>>()
+                    .join("..")
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        assert_eq!(recognize(&image), "ZNX");
+        assert_eq!(recognize("#...\n.#..\n..#.\n...#\n..#.\n.#.."), "?");
+    }
+}
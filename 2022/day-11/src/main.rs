@@ -1,47 +1,184 @@
 use nom::{
   branch::alt,
   bytes::complete::{tag, take_till, take_until},
-  character::complete::{alphanumeric0, digit0, digit1, multispace0, newline},
-  combinator::{map_res, rest},
-  multi::{many0, separated_list1},
+  character::complete::{char, digit0, digit1, multispace0, newline, one_of, space0},
+  combinator::{map, map_res, rest, value},
+  multi::{fold_many0, many0, separated_list1},
   sequence::{delimited, pair, preceded, terminated},
   IResult, Parser,
 };
+use num_bigint::{BigInt, Sign};
+use std::collections::HashMap;
+use std::fmt;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 enum Operation {
-  Mult(u64),
-  Plus(u64),
-  Square,
+  Old,
+  Constant(u64),
+  Plus(Box<Operation>, Box<Operation>),
+  Minus(Box<Operation>, Box<Operation>),
+  Mult(Box<Operation>, Box<Operation>),
+}
+
+trait Worry: Sized + Clone + fmt::Display {
+  fn from_u64(value: u64) -> Self;
+  fn add(&self, rhs: &Self) -> Option<Self>;
+  fn sub(&self, rhs: &Self) -> Option<Self>;
+  fn mul(&self, rhs: &Self) -> Option<Self>;
+  fn rem(&self, modulus: u64) -> Self;
+  fn div(&self, divisor: u64) -> Self;
+  fn divisible_by(&self, divisor: u64) -> bool;
+}
+
+impl Worry for u64 {
+  fn from_u64(value: u64) -> Self {
+    value
+  }
+  fn add(&self, rhs: &Self) -> Option<Self> {
+    self.checked_add(*rhs)
+  }
+  fn sub(&self, rhs: &Self) -> Option<Self> {
+    self.checked_sub(*rhs)
+  }
+  fn mul(&self, rhs: &Self) -> Option<Self> {
+    self.checked_mul(*rhs)
+  }
+  fn rem(&self, modulus: u64) -> Self {
+    self % modulus
+  }
+  fn div(&self, divisor: u64) -> Self {
+    self / divisor
+  }
+  fn divisible_by(&self, divisor: u64) -> bool {
+    self.is_multiple_of(divisor)
+  }
+}
+
+impl Worry for BigInt {
+  fn from_u64(value: u64) -> Self {
+    BigInt::from(value)
+  }
+  fn add(&self, rhs: &Self) -> Option<Self> {
+    Some(self + rhs)
+  }
+  fn sub(&self, rhs: &Self) -> Option<Self> {
+    Some(self - rhs)
+  }
+  fn mul(&self, rhs: &Self) -> Option<Self> {
+    Some(self * rhs)
+  }
+  fn rem(&self, modulus: u64) -> Self {
+    (self % modulus + modulus) % modulus
+  }
+  fn div(&self, divisor: u64) -> Self {
+    let quotient = self / divisor;
+    if self.sign() == Sign::Minus && !self.divisible_by(divisor) {
+      quotient - 1
+    } else {
+      quotient
+    }
+  }
+  fn divisible_by(&self, divisor: u64) -> bool {
+    (self % divisor) == BigInt::ZERO
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum WorryError {
+  Overflow,
+  Underflow,
+}
+
+impl WorryError {
+  fn at(self, round: usize, monkey: usize) -> SimulationError {
+    match self {
+      WorryError::Overflow => SimulationError::Overflow { round, monkey },
+      WorryError::Underflow => SimulationError::Underflow { round, monkey },
+    }
+  }
+}
+
+impl Operation {
+  fn evaluate<W: Worry>(&self, old: &W, modulus: Option<u64>) -> Result<W, WorryError> {
+    use WorryError::{Overflow, Underflow};
+    let reduce = |value: W| modulus.map_or(value.clone(), |m| value.rem(m));
+    Ok(match self {
+      Operation::Old => reduce(old.clone()),
+      Operation::Constant(value) => reduce(W::from_u64(*value)),
+      Operation::Plus(lhs, rhs) => reduce(
+        lhs
+          .evaluate(old, modulus)?
+          .add(&rhs.evaluate(old, modulus)?)
+          .ok_or(Overflow)?,
+      ),
+      Operation::Minus(lhs, rhs) => {
+        let (lhs, rhs) = (lhs.evaluate(old, modulus)?, rhs.evaluate(old, modulus)?);
+        match modulus {
+          Some(m) => lhs
+            .add(&W::from_u64(m))
+            .ok_or(Overflow)?
+            .sub(&rhs)
+            .ok_or(Underflow)?
+            .rem(m),
+          None => lhs.sub(&rhs).ok_or(Underflow)?,
+        }
+      }
+      Operation::Mult(lhs, rhs) => reduce(
+        lhs
+          .evaluate(old, modulus)?
+          .mul(&rhs.evaluate(old, modulus)?)
+          .ok_or(Overflow)?,
+      ),
+    })
+  }
 }
 
 fn from_text(input: &str) -> Result<u64, std::num::ParseIntError> {
-  return input.parse::<u64>();
+  input.parse::<u64>()
 }
 
-fn operator_parser(input: &str) -> IResult<&str, &str> {
-  preceded(
-    pair(multispace0, tag("Operation: new = old ")),
-    alt((tag("+"), tag("*"))),
+fn factor_parser(input: &str) -> IResult<&str, Operation> {
+  delimited(
+    space0,
+    alt((
+      value(Operation::Old, tag("old")),
+      map(map_res(digit1, from_text), Operation::Constant),
+      delimited(char('('), expression_parser, char(')')),
+    )),
+    space0,
+  )
+  .parse(input)
+}
+
+fn term_parser(input: &str) -> IResult<&str, Operation> {
+  let (input, first) = factor_parser(input)?;
+  fold_many0(
+    preceded(char('*'), factor_parser),
+    move || first.clone(),
+    |lhs, rhs| Operation::Mult(Box::new(lhs), Box::new(rhs)),
+  )
+  .parse(input)
+}
+
+fn expression_parser(input: &str) -> IResult<&str, Operation> {
+  let (input, first) = term_parser(input)?;
+  fold_many0(
+    pair(one_of("+-"), term_parser),
+    move || first.clone(),
+    |lhs, (operator, rhs)| match operator {
+      '+' => Operation::Plus(Box::new(lhs), Box::new(rhs)),
+      _ => Operation::Minus(Box::new(lhs), Box::new(rhs)),
+    },
   )
   .parse(input)
 }
 
 fn operation_parser(input: &str) -> IResult<&str, Operation> {
-  let (input, operator) = operator_parser(input)?;
-  let (input, rhs) = delimited(multispace0, alphanumeric0, newline).parse(input)?;
-  if rhs.eq("old") {
-    return Ok((input, Operation::Square));
-  } else {
-    let operand = from_text(rhs).unwrap();
-    if operator.eq("+") {
-      return Ok((input, Operation::Plus(operand)));
-    } else if operator.eq("*") {
-      return Ok((input, Operation::Mult(operand)));
-    } else {
-      todo!("unknown operation");
-    }
-  }
+  preceded(
+    pair(multispace0, tag("Operation: new =")),
+    terminated(expression_parser, newline),
+  )
+  .parse(input)
 }
 
 fn integer_list_from_line(input: &str) -> IResult<&str, Vec<u64>> {
@@ -61,7 +198,7 @@ fn integer_from_line(input: &str) -> IResult<&str, u64> {
   Ok((input, number))
 }
 
-fn monkey_parser(input: &str) -> IResult<&str, Monkey> {
+fn monkey_parser(input: &str) -> IResult<&str, Monkey<u64>> {
   let (input, _) = integer_from_line(input)?;
   let (input, starting_items) = integer_list_from_line(input)?;
   let (input, operation) = operation_parser(input)?;
@@ -80,14 +217,47 @@ fn monkey_parser(input: &str) -> IResult<&str, Monkey> {
   ))
 }
 
-fn parse_monkeys(input: &str) -> IResult<&str, Vec<Monkey>> {
-  let (input, monkeys) = many0(monkey_parser).parse(input)?;
+fn parse_monkeys(input: &str) -> IResult<&str, Vec<Monkey<u64>>> {
+  let (input, mut monkeys) = many0(monkey_parser).parse(input)?;
+  for (id, item) in monkeys
+    .iter_mut()
+    .flat_map(|m| m.items.iter_mut())
+    .enumerate()
+  {
+    item.id = id;
+  }
   Ok((input, monkeys))
 }
 
+#[derive(Debug, Clone)]
+struct Item<W> {
+  id: usize,
+  worry: W,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Event<W> {
+  round: usize,
+  monkey: usize,
+  item: usize,
+  worry_before: W,
+  worry_after: W,
+  target: usize,
+}
+
+impl<W: Worry> fmt::Display for Event<W> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(
+      f,
+      "round {} monkey {} item {}: {} -> {}, thrown to monkey {}",
+      self.round, self.monkey, self.item, self.worry_before, self.worry_after, self.target
+    )
+  }
+}
+
 #[derive(Debug)]
-struct Monkey {
-  items: Vec<u64>,
+struct Monkey<W> {
+  items: Vec<Item<W>>,
   operation: Operation,
   test_mod: u64,
   next_true: usize,
@@ -95,16 +265,19 @@ struct Monkey {
   inspected_items: u64,
 }
 
-impl Monkey {
+impl<W: Worry> Monkey<W> {
   fn create(
-    items: Vec<u64>,
+    items: Vec<W>,
     operation: Operation,
     test_mod: u64,
     next_true: usize,
     next_false: usize,
   ) -> Self {
     Self {
-      items,
+      items: items
+        .into_iter()
+        .map(|worry| Item { id: 0, worry })
+        .collect(),
       operation,
       test_mod,
       next_true,
@@ -112,79 +285,393 @@ impl Monkey {
       inspected_items: 0,
     }
   }
-  fn inspect_and_throw_items(&mut self, observer: &Observer) -> Vec<ThrowInstruction> {
-    let mut throw_instructions: Vec<ThrowInstruction> = Vec::new();
-    self.inspected_items += u64::try_from(self.items.len()).unwrap();
-    for initial_worry_level in self.items.drain(..) {
-      let new_worry_level = observer.observe_inspection(initial_worry_level, &self.operation);
-      throw_instructions.push((
-        new_worry_level,
-        if new_worry_level % self.test_mod == 0 {
-          self.next_true
-        } else {
-          self.next_false
-        },
-      ));
+  fn with_items<V: Worry>(self, convert: impl Fn(W) -> V) -> Monkey<V> {
+    Monkey {
+      items: self
+        .items
+        .into_iter()
+        .map(|item| Item {
+          id: item.id,
+          worry: convert(item.worry),
+        })
+        .collect(),
+      operation: self.operation,
+      test_mod: self.test_mod,
+      next_true: self.next_true,
+      next_false: self.next_false,
+      inspected_items: self.inspected_items,
     }
-    return throw_instructions;
+  }
+  fn inspect(&self, worry_level: &W, observer: &Observer) -> Result<(W, usize), WorryError> {
+    let new_worry_level = observer.observe_inspection(worry_level, &self.operation)?;
+    let target = if new_worry_level.divisible_by(self.test_mod) {
+      self.next_true
+    } else {
+      self.next_false
+    };
+    Ok((new_worry_level, target))
   }
 }
 
 struct Observer {
   relief_factor: u64,
-  test_prod: u64,
+  modulus: Option<u64>,
 }
 
 impl Observer {
-  fn create(monkeys: &Vec<Monkey>, relief_factor: u64) -> Self {
-    let test_prod = monkeys.iter().map(|m| m.test_mod).reduce(|a, b| a * b);
+  fn create<W>(monkeys: &[Monkey<W>], relief_factor: u64) -> Result<Self, SimulationError> {
+    if relief_factor != 1 {
+      return Ok(Self::exact(relief_factor));
+    }
+    let modulus = monkeys
+      .iter()
+      .try_fold(1u64, |prod, m| prod.checked_mul(m.test_mod))
+      .ok_or(SimulationError::ModulusOverflow)?;
+    Ok(Self {
+      relief_factor,
+      modulus: Some(modulus),
+    })
+  }
+  fn exact(relief_factor: u64) -> Self {
     Self {
-      test_prod: Option::expect(test_prod, "could not multiply test conditions"),
       relief_factor,
+      modulus: None,
     }
   }
-  fn observe_inspection(&self, worry_level: u64, operation: &Operation) -> u64 {
-    let new_worry_level = match operation {
-      Operation::Mult(factor) => (worry_level * factor) % self.test_prod,
-      Operation::Plus(addend) => (worry_level + addend) % self.test_prod,
-      Operation::Square => (worry_level * worry_level) % self.test_prod,
-    };
-    return (new_worry_level / self.relief_factor) % self.test_prod;
+  fn observe_inspection<W: Worry>(
+    &self,
+    worry_level: &W,
+    operation: &Operation,
+  ) -> Result<W, WorryError> {
+    Ok(
+      operation
+        .evaluate(worry_level, self.modulus)?
+        .div(self.relief_factor),
+    )
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Config {
+  rounds: usize,
+  relief_factor: u64,
+  top: usize,
+}
+
+impl Config {
+  const PART_ONE: Config = Config {
+    rounds: 20,
+    relief_factor: 3,
+    top: 2,
+  };
+  const PART_TWO: Config = Config {
+    rounds: 10000,
+    relief_factor: 1,
+    top: 2,
+  };
+}
+
+#[derive(Debug, PartialEq)]
+enum SimulationError {
+  Parse(String),
+  NoMonkeys,
+  InvalidTarget { monkey: usize, target: usize },
+  ZeroDivisor { monkey: usize },
+  ZeroRelief,
+  ZeroTop,
+  TooFewMonkeys { needed: usize, found: usize },
+  ModulusOverflow,
+  Overflow { round: usize, monkey: usize },
+  Underflow { round: usize, monkey: usize },
+  BusinessOverflow,
+  UnboundedWorry,
+}
+
+impl fmt::Display for SimulationError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      SimulationError::Parse(line) => write!(f, "could not parse monkey at `{}`", line),
+      SimulationError::NoMonkeys => write!(f, "input contains no monkeys"),
+      SimulationError::InvalidTarget { monkey, target } => write!(
+        f,
+        "monkey {} throws to monkey {}, which does not exist",
+        monkey, target
+      ),
+      SimulationError::ZeroDivisor { monkey } => {
+        write!(f, "monkey {} tests divisibility by zero", monkey)
+      }
+      SimulationError::ZeroRelief => write!(f, "relief factor must be at least 1"),
+      SimulationError::ZeroTop => write!(f, "at least one monkey must be combined"),
+      SimulationError::TooFewMonkeys { needed, found } => write!(
+        f,
+        "need {} monkeys to combine but only {} are present",
+        needed, found
+      ),
+      SimulationError::ModulusOverflow => {
+        write!(f, "product of the divisibility tests overflows")
+      }
+      SimulationError::Overflow { round, monkey } => write!(
+        f,
+        "worry level overflow in round {} while monkey {} was inspecting",
+        round, monkey
+      ),
+      SimulationError::Underflow { round, monkey } => write!(
+        f,
+        "worry level went negative in round {} while monkey {} was inspecting",
+        round, monkey
+      ),
+      SimulationError::BusinessOverflow => write!(f, "monkey business overflows"),
+      SimulationError::UnboundedWorry => {
+        write!(f, "cycles can only be detected with a relief factor of 1")
+      }
+    }
+  }
+}
+
+fn validate_monkeys<W>(monkeys: &[Monkey<W>]) -> Result<(), SimulationError> {
+  if monkeys.is_empty() {
+    return Err(SimulationError::NoMonkeys);
+  }
+  for (m_i, monkey) in monkeys.iter().enumerate() {
+    if monkey.test_mod == 0 {
+      return Err(SimulationError::ZeroDivisor { monkey: m_i });
+    }
+    for target in [monkey.next_true, monkey.next_false] {
+      if target >= monkeys.len() {
+        return Err(SimulationError::InvalidTarget {
+          monkey: m_i,
+          target,
+        });
+      }
+    }
   }
+  Ok(())
 }
 
-type ThrowInstruction = (u64, usize);
+fn validate_config(config: &Config, monkeys: usize) -> Result<(), SimulationError> {
+  if config.relief_factor == 0 {
+    return Err(SimulationError::ZeroRelief);
+  }
+  if config.top == 0 {
+    return Err(SimulationError::ZeroTop);
+  }
+  if config.top > monkeys {
+    return Err(SimulationError::TooFewMonkeys {
+      needed: config.top,
+      found: monkeys,
+    });
+  }
+  Ok(())
+}
 
-fn simulate_round(monkeys: &mut Vec<Monkey>, observer: &Observer) {
+fn load_monkeys(input: &str, config: &Config) -> Result<Vec<Monkey<u64>>, SimulationError> {
+  let (remaining, monkeys) =
+    parse_monkeys(input).map_err(|err| SimulationError::Parse(err.to_string()))?;
+  if let Some(line) = remaining.lines().find(|line| !line.trim().is_empty()) {
+    return Err(SimulationError::Parse(line.trim().to_string()));
+  }
+  validate_monkeys(&monkeys)?;
+  validate_config(config, monkeys.len())?;
+  Ok(monkeys)
+}
+
+fn top_monkey_business(mut inspected: Vec<u64>, top: usize) -> Result<u64, SimulationError> {
+  inspected.sort_by_key(|&count| std::cmp::Reverse(count));
+  inspected[..top]
+    .iter()
+    .try_fold(1u64, |business, &count| business.checked_mul(count))
+    .ok_or(SimulationError::BusinessOverflow)
+}
+
+fn simulate_round<W: Worry>(
+  monkeys: &mut [Monkey<W>],
+  observer: &Observer,
+  round: usize,
+  mut events: Option<&mut Vec<Event<W>>>,
+) -> Result<(), SimulationError> {
   for m_i in 0..monkeys.len() {
-    let monkey = &mut monkeys[m_i];
-    let throw_instructions = monkey.inspect_and_throw_items(observer);
-    for (item, monkey_index) in throw_instructions {
-      monkeys[monkey_index].items.push(item);
+    let items = std::mem::take(&mut monkeys[m_i].items);
+    monkeys[m_i].inspected_items += u64::try_from(items.len()).unwrap();
+    for item in items {
+      let (worry_after, target) = monkeys[m_i]
+        .inspect(&item.worry, observer)
+        .map_err(|err| err.at(round, m_i))?;
+      let thrown = Item {
+        id: item.id,
+        worry: worry_after,
+      };
+      if let Some(events) = events.as_deref_mut() {
+        events.push(Event {
+          round,
+          monkey: m_i,
+          item: item.id,
+          worry_before: item.worry,
+          worry_after: thrown.worry.clone(),
+          target,
+        });
+      }
+      monkeys[target].items.push(thrown);
     }
   }
+  Ok(())
 }
 
-fn monkey_business(input: &str, n_rounds: usize, relief_factor: u64) -> u64 {
-  let (_, mut monkeys) = parse_monkeys(input).unwrap();
-  let observer = Observer::create(&monkeys, relief_factor);
-  for _ in 0..n_rounds {
-    simulate_round(&mut monkeys, &observer);
+fn holdings_report<W: Worry>(monkeys: &[Monkey<W>], round: usize) -> String {
+  let mut report = format!(
+    "After round {}, the monkeys are holding items with these worry levels:",
+    round
+  );
+  for (m_i, monkey) in monkeys.iter().enumerate() {
+    let worries: Vec<String> = monkey
+      .items
+      .iter()
+      .map(|item| item.worry.to_string())
+      .collect();
+    report.push_str(&format!("\nMonkey {}: {}", m_i, worries.join(", ")));
   }
-  monkeys.sort_by(|a, b| b.inspected_items.cmp(&a.inspected_items));
-  let first_two = &monkeys[..2];
-  match first_two {
-    [m1, m2] => return m1.inspected_items * m2.inspected_items,
-    _ => todo!("will we ever have less than two monkeys?"),
+  report
+}
+
+struct History {
+  events: Vec<Event<u64>>,
+  reports: Vec<String>,
+}
+
+fn record_history(
+  input: &str,
+  config: &Config,
+  log_events: bool,
+) -> Result<History, SimulationError> {
+  let mut monkeys = load_monkeys(input, config)?;
+  let observer = Observer::create(&monkeys, config.relief_factor)?;
+  let mut history = History {
+    events: Vec::new(),
+    reports: Vec::new(),
+  };
+  for round in 1..=config.rounds {
+    let events = log_events.then_some(&mut history.events);
+    simulate_round(&mut monkeys, &observer, round, events)?;
+    history.reports.push(holdings_report(&monkeys, round));
+  }
+  Ok(history)
+}
+
+struct ItemCycle {
+  rounds: Vec<Vec<usize>>,
+  cycle_start: usize,
+}
+
+impl ItemCycle {
+  fn detect(
+    monkeys: &[Monkey<u64>],
+    observer: &Observer,
+    mut monkey: usize,
+    mut worry: u64,
+  ) -> Result<Self, SimulationError> {
+    let mut seen: HashMap<(usize, u64), usize> = HashMap::new();
+    let mut rounds = Vec::new();
+    loop {
+      if let Some(&cycle_start) = seen.get(&(monkey, worry)) {
+        return Ok(Self {
+          rounds,
+          cycle_start,
+        });
+      }
+      seen.insert((monkey, worry), rounds.len());
+      let round = rounds.len() + 1;
+      let mut inspections = Vec::new();
+      loop {
+        inspections.push(monkey);
+        let (worry_after, target) = monkeys[monkey]
+          .inspect(&worry, observer)
+          .map_err(|err| err.at(round, monkey))?;
+        let inspected_again = target > monkey;
+        (monkey, worry) = (target, worry_after);
+        if !inspected_again {
+          break;
+        }
+      }
+      rounds.push(inspections);
+    }
+  }
+  fn inspections(&self, n_rounds: usize, counts: &mut [u64]) {
+    let period = self.rounds.len() - self.cycle_start;
+    let tally = |rounds: &[Vec<usize>], times: u64, counts: &mut [u64]| {
+      for &monkey in rounds.iter().flatten() {
+        counts[monkey] += times;
+      }
+    };
+    if n_rounds <= self.cycle_start {
+      tally(&self.rounds[..n_rounds], 1, counts);
+      return;
+    }
+    let remaining = n_rounds - self.cycle_start;
+    let cycle = &self.rounds[self.cycle_start..];
+    tally(&self.rounds[..self.cycle_start], 1, counts);
+    tally(cycle, u64::try_from(remaining / period).unwrap(), counts);
+    tally(&cycle[..remaining % period], 1, counts);
+  }
+}
+
+fn extrapolated_monkey_business(input: &str, config: &Config) -> Result<u64, SimulationError> {
+  if config.relief_factor != 1 {
+    return Err(SimulationError::UnboundedWorry);
+  }
+  let monkeys = load_monkeys(input, config)?;
+  let observer = Observer::create(&monkeys, config.relief_factor)?;
+  let modulus = observer.modulus.ok_or(SimulationError::UnboundedWorry)?;
+  let mut inspected = vec![0; monkeys.len()];
+  for (m_i, monkey) in monkeys.iter().enumerate() {
+    for item in &monkey.items {
+      let worry = item.worry % modulus;
+      ItemCycle::detect(&monkeys, &observer, m_i, worry)?
+        .inspections(config.rounds, &mut inspected);
+    }
   }
+  top_monkey_business(inspected, config.top)
+}
+
+fn simulate<W: Worry>(
+  monkeys: &mut [Monkey<W>],
+  observer: &Observer,
+  config: &Config,
+) -> Result<u64, SimulationError> {
+  for round in 1..=config.rounds {
+    simulate_round(monkeys, observer, round, None)?;
+  }
+  top_monkey_business(
+    monkeys.iter().map(|m| m.inspected_items).collect(),
+    config.top,
+  )
+}
+
+fn monkey_business(input: &str, config: &Config) -> Result<u64, SimulationError> {
+  let mut monkeys = load_monkeys(input, config)?;
+  let observer = Observer::create(&monkeys, config.relief_factor)?;
+  simulate(&mut monkeys, &observer, config)
+}
+
+fn exact_monkey_business(input: &str, config: &Config) -> Result<u64, SimulationError> {
+  let monkeys = load_monkeys(input, config)?;
+  let observer = Observer::exact(config.relief_factor);
+  let mut monkeys: Vec<Monkey<BigInt>> = monkeys
+    .into_iter()
+    .map(|m| m.with_items(BigInt::from))
+    .collect();
+  simulate(&mut monkeys, &observer, config)
 }
 
 fn part_one(input: &str) -> u64 {
-  monkey_business(input, 20, 3)
+  monkey_business(input, &Config::PART_ONE).unwrap()
 }
 
 fn part_two(input: &str) -> u64 {
-  monkey_business(input, 10000, 1)
+  monkey_business(input, &Config::PART_TWO).unwrap()
+}
+
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+  let pos = args.iter().position(|arg| arg == name)?;
+  args.get(pos + 1).map(String::as_str)
 }
 
 fn main() {
@@ -192,6 +679,65 @@ fn main() {
   println!("{}", part_one(input));
   println!();
   println!("{}", part_two(input));
+
+  let args: Vec<String> = std::env::args().skip(1).collect();
+  let mut config = Config::PART_ONE;
+  if let Some(rounds) = option_value(&args, "--rounds") {
+    config.rounds = rounds.parse().unwrap();
+  }
+  if let Some(relief_factor) = option_value(&args, "--relief") {
+    config.relief_factor = relief_factor.parse().unwrap();
+  }
+  if let Some(top) = option_value(&args, "--top") {
+    config.top = top.parse().unwrap();
+  }
+  let report = |mode: &str, result: Result<u64, SimulationError>| match result {
+    Ok(business) => println!("{}: {}", mode, business),
+    Err(err) => println!("{}: {}", mode, err),
+  };
+  if ["--rounds", "--relief", "--top"]
+    .iter()
+    .any(|flag| args.iter().any(|arg| arg == flag))
+  {
+    println!();
+    report("custom", monkey_business(input, &config));
+  }
+  if let Some(rounds) = option_value(&args, "--validate") {
+    let config = Config {
+      rounds: rounds.parse().unwrap(),
+      relief_factor: 1,
+      ..config
+    };
+    println!();
+    report("checked", monkey_business(input, &config));
+    report("exact", exact_monkey_business(input, &config));
+  }
+  if let Some(rounds) = option_value(&args, "--history") {
+    let config = Config {
+      rounds: rounds.parse().unwrap(),
+      ..config
+    };
+    let log = args.iter().any(|arg| arg == "--events");
+    let history = record_history(input, &config, log).unwrap();
+    for round in 1..=config.rounds {
+      println!();
+      if log {
+        for event in history.events.iter().filter(|e| e.round == round) {
+          println!("{}", event);
+        }
+      }
+      println!("{}", history.reports[round - 1]);
+    }
+  }
+  if let Some(rounds) = option_value(&args, "--extrapolate") {
+    let config = Config {
+      rounds: rounds.parse().unwrap(),
+      relief_factor: 1,
+      ..config
+    };
+    println!();
+    report("extrapolated", extrapolated_monkey_business(input, &config));
+  }
 }
 
 #[cfg(test)]
@@ -229,4 +775,272 @@ Monkey 3:
   fn part_one_example() {
     assert_eq!(part_one(INPUT), 10605);
   }
+
+  #[test]
+  fn part_two_example() {
+    assert_eq!(part_two(INPUT), 2713310158);
+  }
+
+  #[test]
+  fn operation_expressions() {
+    let parse = |text| expression_parser(text).unwrap().1;
+    assert_eq!(parse(" old - 3").evaluate(&10u64, None), Ok(7));
+    assert_eq!(parse(" old * old + 2").evaluate(&10u64, None), Ok(102));
+    assert_eq!(
+      parse(" (old + 1) * (2 + old)").evaluate(&10u64, None),
+      Ok(132)
+    );
+    assert_eq!(parse(" 2 * old - old").evaluate(&10u64, None), Ok(10));
+    assert_eq!(parse(" old - 3").evaluate(&1u64, Some(7)), Ok(5));
+    assert_eq!(
+      parse(" old * old + 2").evaluate(&10u64, Some(7)),
+      Ok(102 % 7)
+    );
+    assert_eq!(
+      parse(" old - 3").evaluate(&1u64, None),
+      Err(WorryError::Underflow)
+    );
+    assert_eq!(
+      parse(" old * old").evaluate(&u64::MAX, None),
+      Err(WorryError::Overflow)
+    );
+  }
+
+  #[test]
+  fn overflow_detection() {
+    let input = "Monkey 0:
+  Starting items: 10
+  Operation: new = old * old
+  Test: divisible by 7
+    If true: throw to monkey 0
+    If false: throw to monkey 0
+
+Monkey 1:
+  Starting items: 3
+  Operation: new = old + 1
+  Test: divisible by 2
+    If true: throw to monkey 1
+    If false: throw to monkey 1";
+    let config = Config {
+      rounds: 10,
+      relief_factor: 2,
+      top: 2,
+    };
+    assert_eq!(
+      monkey_business(input, &config),
+      Err(SimulationError::Overflow {
+        round: 5,
+        monkey: 0
+      })
+    );
+    assert_eq!(exact_monkey_business(input, &config), Ok(10 * 10));
+    assert_eq!(
+      monkey_business(input, &Config { top: 0, ..config }),
+      Err(SimulationError::ZeroTop)
+    );
+    let config = Config {
+      relief_factor: 1,
+      ..Config::PART_ONE
+    };
+    assert_eq!(
+      exact_monkey_business(INPUT, &config),
+      monkey_business(INPUT, &config)
+    );
+  }
+
+  #[test]
+  fn large_divisors() {
+    let input = "Monkey 0:
+  Starting items: 4294967311, 5
+  Operation: new = old * 2
+  Test: divisible by 4294967311
+    If true: throw to monkey 1
+    If false: throw to monkey 1
+
+Monkey 1:
+  Starting items: 7
+  Operation: new = old + 1
+  Test: divisible by 4294967357
+    If true: throw to monkey 0
+    If false: throw to monkey 0";
+    let config = Config {
+      rounds: 3,
+      relief_factor: 1,
+      top: 2,
+    };
+    assert_eq!(
+      monkey_business(input, &config),
+      Err(SimulationError::ModulusOverflow)
+    );
+    assert_eq!(exact_monkey_business(input, &config), Ok(8 * 9));
+    let config = Config {
+      relief_factor: 3,
+      ..config
+    };
+    assert_eq!(monkey_business(input, &config), Ok(8 * 9));
+    assert_eq!(exact_monkey_business(input, &config), Ok(8 * 9));
+  }
+
+  #[test]
+  fn negative_worry() {
+    let input = "Monkey 0:
+  Starting items: 5
+  Operation: new = old - 3
+  Test: divisible by 2
+    If true: throw to monkey 1
+    If false: throw to monkey 1
+
+Monkey 1:
+  Starting items: 10
+  Operation: new = old - 1
+  Test: divisible by 3
+    If true: throw to monkey 0
+    If false: throw to monkey 0";
+    let config = Config {
+      rounds: 3,
+      relief_factor: 1,
+      top: 1,
+    };
+    assert_eq!(monkey_business(input, &config), Ok(6));
+    assert_eq!(exact_monkey_business(input, &config), Ok(6));
+    let config = Config {
+      relief_factor: 2,
+      ..config
+    };
+    let err = monkey_business(input, &config).unwrap_err();
+    assert_eq!(
+      err,
+      SimulationError::Underflow {
+        round: 2,
+        monkey: 0
+      }
+    );
+    assert_eq!(
+      err.to_string(),
+      "worry level went negative in round 2 while monkey 0 was inspecting"
+    );
+    assert_eq!(exact_monkey_business(input, &config), Ok(6));
+    assert_eq!(BigInt::from(-7).div(2), BigInt::from(-4));
+    assert_eq!(BigInt::from(-7).rem(3), BigInt::from(2));
+  }
+
+  #[test]
+  fn round_history() {
+    let history = record_history(
+      INPUT,
+      &Config {
+        rounds: 2,
+        ..Config::PART_ONE
+      },
+      true,
+    )
+    .unwrap();
+    assert_eq!(
+      history.reports[0],
+      "After round 1, the monkeys are holding items with these worry levels:
+Monkey 0: 20, 23, 27, 26
+Monkey 1: 2080, 25, 167, 207, 401, 1046
+Monkey 2: 
+Monkey 3: "
+    );
+    assert_eq!(
+      history.events[0],
+      Event {
+        round: 1,
+        monkey: 0,
+        item: 0,
+        worry_before: 79,
+        worry_after: 500,
+        target: 3
+      }
+    );
+    assert_eq!(
+      history.events[0].to_string(),
+      "round 1 monkey 0 item 0: 79 -> 500, thrown to monkey 3"
+    );
+    assert_eq!(history.events.iter().filter(|e| e.round == 1).count(), 14);
+    let quiet = record_history(INPUT, &Config::PART_ONE, false).unwrap();
+    assert!(quiet.events.is_empty());
+    assert_eq!(quiet.reports.len(), 20);
+  }
+
+  #[test]
+  fn cycle_extrapolation() {
+    for rounds in [1, 20, 1000, 10000] {
+      let config = Config {
+        rounds,
+        ..Config::PART_TWO
+      };
+      assert_eq!(
+        extrapolated_monkey_business(INPUT, &config),
+        monkey_business(INPUT, &config)
+      );
+    }
+  }
+
+  #[test]
+  fn configurable_business() {
+    let config = |rounds, relief_factor, top| Config {
+      rounds,
+      relief_factor,
+      top,
+    };
+    assert_eq!(monkey_business(INPUT, &config(20, 3, 1)), Ok(105));
+    assert_eq!(
+      monkey_business(INPUT, &config(20, 3, 4)),
+      Ok(101 * 95 * 7 * 105)
+    );
+    assert_eq!(
+      monkey_business(INPUT, &config(20, 3, 5)),
+      Err(SimulationError::TooFewMonkeys {
+        needed: 5,
+        found: 4
+      })
+    );
+    assert_eq!(
+      monkey_business(INPUT, &config(20, 3, 0)),
+      Err(SimulationError::ZeroTop)
+    );
+    assert_eq!(
+      monkey_business(INPUT, &config(20, 0, 2)),
+      Err(SimulationError::ZeroRelief)
+    );
+    assert_eq!(
+      exact_monkey_business(INPUT, &config(20, 0, 2)),
+      Err(SimulationError::ZeroRelief)
+    );
+    assert_eq!(
+      extrapolated_monkey_business(INPUT, &config(20, 1, 0)),
+      Err(SimulationError::ZeroTop)
+    );
+    assert_eq!(
+      monkey_business("", &Config::PART_ONE),
+      Err(SimulationError::NoMonkeys)
+    );
+    let single = "Monkey 0:
+  Starting items: 1, 2
+  Operation: new = old + 1
+  Test: divisible by 2
+    If true: throw to monkey 0
+    If false: throw to monkey 0";
+    assert_eq!(monkey_business(single, &config(3, 1, 1)), Ok(6));
+    assert_eq!(
+      monkey_business(single, &Config::PART_ONE),
+      Err(SimulationError::TooFewMonkeys {
+        needed: 2,
+        found: 1
+      })
+    );
+    assert_eq!(
+      monkey_business(&INPUT.replace("monkey 3", "monkey 4"), &Config::PART_ONE),
+      Err(SimulationError::InvalidTarget {
+        monkey: 0,
+        target: 4
+      })
+    );
+    assert_eq!(
+      monkey_business(&format!("{}\n\nMonkey ?", INPUT), &Config::PART_ONE),
+      Err(SimulationError::Parse("Monkey ?".to_string()))
+    );
+  }
 }
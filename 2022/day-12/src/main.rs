@@ -1,3 +1,6 @@
+mod render;
+mod search;
+
 fn parse_hill_height_matrix(input: &str) -> Vec<Vec<char>> {
   input
     .lines()
@@ -8,7 +11,7 @@ fn parse_hill_height_matrix(input: &str) -> Vec<Vec<char>> {
 
 type Position = (usize, usize);
 
-fn find_character_positions(grid: &Vec<Vec<char>>, target_char: char) -> Vec<(usize, usize)> {
+fn find_character_positions(grid: &[Vec<char>], target_char: char) -> Vec<(usize, usize)> {
   grid
     .iter()
     .enumerate()
@@ -26,118 +29,217 @@ fn find_character_positions(grid: &Vec<Vec<char>>, target_char: char) -> Vec<(us
     .collect()
 }
 
-fn within_bounds<T>(grid: &Vec<Vec<T>>, position: (i32, i32)) -> bool {
-  return position.0 >= 0
+fn elevation(c: char) -> u8 {
+  match c {
+    'S' => b'a',
+    'E' => b'z',
+    _ => c as u8,
+  }
+}
+
+fn within_bounds<T>(grid: &[Vec<T>], position: (i32, i32)) -> bool {
+  position.0 >= 0
     && position.1 >= 0
     && (position.0 as usize) < grid.len()
-    && (position.1 as usize) < grid[position.0 as usize].len();
+    && (position.1 as usize) < grid[position.0 as usize].len()
 }
 
-fn legal_move(from: char, to: char) -> bool {
-  if from.is_ascii_lowercase() && to.is_ascii_lowercase() {
-    return (to as u32) <= (from as u32) + 1;
-  }
-  return from == 'S' || to == 'E' && (from == 'y' || from == 'z');
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ClimbingPolicy {
+  max_ascent: u8,
+  max_descent: u8,
+  diagonal: bool,
 }
 
-fn can_move(
-  from: Position,
-  to: (i32, i32),
-  grid: &Vec<Vec<char>>,
-  visited: &Vec<Vec<bool>>,
-) -> bool {
-  if within_bounds(grid, to) {
-    let pos_to = (to.0 as usize, to.1 as usize);
-    return legal_move(grid[from.0][from.1], grid[pos_to.0][pos_to.1])
-      && !visited[pos_to.0][pos_to.1];
+impl Default for ClimbingPolicy {
+  fn default() -> Self {
+    Self {
+      max_ascent: 1,
+      max_descent: u8::MAX,
+      diagonal: false,
+    }
   }
-  false
 }
 
-fn possible_moves(
-  grid: &Vec<Vec<char>>,
-  visited: &Vec<Vec<bool>>,
-  position: Position,
-) -> Vec<Position> {
-  let deltas = [-1, 1];
-  let mut moves: Vec<Position> = Vec::new();
-  for delta in deltas {
-    // vertical
-    let p_pos = (position.0 as i32 + delta, position.1 as i32);
-    if can_move(position, p_pos, grid, visited) {
-      moves.push((p_pos.0 as usize, p_pos.1 as usize));
-    }
-    // horizontal
-    let p_pos = (position.0 as i32, position.1 as i32 + delta);
-    if can_move(position, p_pos, grid, visited) {
-      moves.push((p_pos.0 as usize, p_pos.1 as usize));
+const ORTHOGONAL: [(i32, i32); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+const DIAGONAL: [(i32, i32); 4] = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
+
+impl ClimbingPolicy {
+  fn legal_move(&self, from: char, to: char) -> bool {
+    let (from, to) = (elevation(from), elevation(to));
+    if to >= from {
+      to - from <= self.max_ascent
+    } else {
+      from - to <= self.max_descent
     }
   }
-  moves
-}
-
-fn shortest_path_length(grid: &Vec<Vec<char>>, start: &Position, target: &Position) -> Option<u32> {
-  let mut visited: Vec<Vec<bool>> = grid.iter().map(|row| vec![false; row.len()]).collect();
-  visited[start.0][start.1] = true;
-  let mut depth = 0;
-  let mut queue: Vec<Position> = Vec::new();
-  queue.push(*start);
-  while !queue.is_empty() {
-    let mut new_queue: Vec<Position> = Vec::new();
-    depth += 1;
-    for (i, j) in queue.drain(..) {
-      let mut moves = possible_moves(grid, &visited, (i, j));
-      for (i_a, j_a) in moves.iter() {
-        visited[*i_a][*j_a] = true;
-        if *i_a == target.0 && *j_a == target.1 {
-          return Some(depth);
-        }
-      }
-      new_queue.append(&mut moves);
+  fn deltas(&self) -> Vec<(i32, i32)> {
+    let mut deltas = ORTHOGONAL.to_vec();
+    if self.diagonal {
+      deltas.extend(DIAGONAL);
     }
-    queue = new_queue;
+    deltas
   }
-  None
 }
 
-fn part_one(input: &str) -> u32 {
-  let grid = parse_hill_height_matrix(input);
+fn neighbours(grid: &[Vec<char>], policy: &ClimbingPolicy, position: Position) -> Vec<Position> {
+  policy
+    .deltas()
+    .iter()
+    .map(|(di, dj)| (position.0 as i32 + di, position.1 as i32 + dj))
+    .filter(|&p_pos| within_bounds(grid, p_pos))
+    .map(|(i, j)| (i as usize, j as usize))
+    .collect()
+}
+
+fn possible_moves(
+  grid: &[Vec<char>],
+  policy: &ClimbingPolicy,
+  position: Position,
+) -> Vec<Position> {
+  neighbours(grid, policy, position)
+    .into_iter()
+    .filter(|to| policy.legal_move(grid[position.0][position.1], grid[to.0][to.1]))
+    .collect()
+}
+
+fn reverse_moves(grid: &[Vec<char>], policy: &ClimbingPolicy, position: Position) -> Vec<Position> {
+  neighbours(grid, policy, position)
+    .into_iter()
+    .filter(|from| policy.legal_move(grid[from.0][from.1], grid[position.0][position.1]))
+    .collect()
+}
+
+fn shortest_path(
+  grid: &[Vec<char>],
+  policy: &ClimbingPolicy,
+  start: &Position,
+  target: &Position,
+) -> Option<Vec<Position>> {
+  search::bfs(
+    *start,
+    |&position| possible_moves(grid, policy, position),
+    |position| position == target,
+  )
+}
+
+fn shortest_path_length(
+  grid: &[Vec<char>],
+  policy: &ClimbingPolicy,
+  start: &Position,
+  target: &Position,
+) -> Option<u32> {
+  shortest_path(grid, policy, start, target).map(|path| path.len() as u32 - 1)
+}
 
-  let start = *find_character_positions(&grid, 'S')
+fn distance_field(grid: &[Vec<char>], policy: &ClimbingPolicy) -> Vec<Vec<Option<u32>>> {
+  let (_, end) = find_start_and_end(grid);
+  let distances = search::bfs_distances(end, |&position| reverse_moves(grid, policy, position));
+  grid
+    .iter()
+    .enumerate()
+    .map(|(i, row)| {
+      (0..row.len())
+        .map(|j| distances.get(&(i, j)).map(|&d| d as u32))
+        .collect()
+    })
+    .collect()
+}
+
+fn format_distance_field(field: &[Vec<Option<u32>>]) -> String {
+  let width = field
+    .iter()
+    .flatten()
+    .flatten()
+    .max()
+    .map_or(1, |max| max.to_string().len());
+  field
+    .iter()
+    .map(|row| {
+      row
+        .iter()
+        .map(|distance| match distance {
+          Some(distance) => format!("{:>width$}", distance, width = width),
+          None => format!("{:>width$}", "-", width = width),
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+    })
+    .collect::<Vec<String>>()
+    .join("\n")
+}
+
+fn weighted_path_length(
+  grid: &[Vec<char>],
+  policy: &ClimbingPolicy,
+  start: &Position,
+  target: &Position,
+  algorithm: &str,
+) -> Option<u32> {
+  let weighted_moves = |&position: &Position| {
+    possible_moves(grid, policy, position)
+      .into_iter()
+      .map(|next| (next, 1))
+  };
+  let is_target = |position: &Position| position == target;
+  let heuristic = |position: &Position| {
+    let (di, dj) = (position.0.abs_diff(target.0), position.1.abs_diff(target.1));
+    (if policy.diagonal { di.max(dj) } else { di + dj }) as u32
+  };
+  let (_, cost) = match algorithm {
+    "dijkstra" => search::dijkstra(*start, weighted_moves, is_target)?,
+    "astar" => search::astar(*start, weighted_moves, heuristic, is_target)?,
+    _ => panic!("unknown search algorithm {}", algorithm),
+  };
+  Some(cost)
+}
+
+fn find_start_and_end(grid: &[Vec<char>]) -> (Position, Position) {
+  let start = *find_character_positions(grid, 'S')
     .first()
     .expect("S not found");
 
-  let end = *find_character_positions(&grid, 'E')
+  let end = *find_character_positions(grid, 'E')
     .first()
     .expect("E not found");
 
+  (start, end)
+}
+
+fn fewest_steps_from_start(grid: &[Vec<char>], policy: &ClimbingPolicy) -> Option<u32> {
+  let (start, end) = find_start_and_end(grid);
+  shortest_path_length(grid, policy, &start, &end)
+}
+
+fn fewest_steps_from_lowest(grid: &[Vec<char>], policy: &ClimbingPolicy) -> Option<u32> {
+  let (_, end) = find_start_and_end(grid);
+  let path = search::bfs(
+    end,
+    |&position| reverse_moves(grid, policy, position),
+    |&(i, j)| elevation(grid[i][j]) == b'a',
+  )?;
+  Some(path.len() as u32 - 1)
+}
+
+fn part_one(input: &str) -> u32 {
+  let grid = parse_hill_height_matrix(input);
   Option::expect(
-    shortest_path_length(&grid, &start, &end),
+    fewest_steps_from_start(&grid, &ClimbingPolicy::default()),
     "could not reach end",
   )
 }
 
 fn part_two(input: &str) -> u32 {
   let grid = parse_hill_height_matrix(input);
+  Option::expect(
+    fewest_steps_from_lowest(&grid, &ClimbingPolicy::default()),
+    "no end found",
+  )
+}
 
-  let s_start = *find_character_positions(&grid, 'S')
-    .first()
-    .expect("S not found");
-  let a_starts = find_character_positions(&grid, 'a');
-
-  let mut starts = vec![s_start];
-  starts.extend(&a_starts);
-
-  let end = *find_character_positions(&grid, 'E')
-    .first()
-    .expect("E not found");
-
-  let res = starts
-    .iter()
-    .filter_map(|start| shortest_path_length(&grid, &start, &end))
-    .min();
-
-  Option::expect(res, "no end found")
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+  let pos = args.iter().position(|arg| arg == name)?;
+  args.get(pos + 1).map(String::as_str)
 }
 
 fn main() {
@@ -145,6 +247,50 @@ fn main() {
   println!("{}", part_one(input));
   println!();
   println!("{}", part_two(input));
+
+  let args: Vec<String> = std::env::args().skip(1).collect();
+  let mut policy = ClimbingPolicy::default();
+  if let Some(ascent) = option_value(&args, "--ascent") {
+    policy.max_ascent = ascent.parse().unwrap();
+  }
+  if let Some(descent) = option_value(&args, "--descent") {
+    policy.max_descent = descent.parse().unwrap();
+  }
+  policy.diagonal = args.iter().any(|arg| arg == "--diagonal");
+  let grid = parse_hill_height_matrix(input);
+  if policy != ClimbingPolicy::default() {
+    println!();
+    println!("start: {:?}", fewest_steps_from_start(&grid, &policy));
+    println!("lowest: {:?}", fewest_steps_from_lowest(&grid, &policy));
+  }
+  if args.iter().any(|arg| arg == "--field") {
+    println!();
+    println!("{}", format_distance_field(&distance_field(&grid, &policy)));
+  }
+  if let Some(algorithm) = option_value(&args, "--search") {
+    let (start, end) = find_start_and_end(&grid);
+    println!();
+    println!(
+      "{}: {:?}",
+      algorithm,
+      weighted_path_length(&grid, &policy, &start, &end, algorithm)
+    );
+  }
+  let color = args.iter().any(|arg| arg == "--color");
+  let svg = option_value(&args, "--svg");
+  if color || svg.is_some() || args.iter().any(|arg| arg == "--draw") {
+    let (start, end) = find_start_and_end(&grid);
+    let path = shortest_path(&grid, &policy, &start, &end).expect("could not reach end");
+    println!();
+    if color {
+      println!("{}", render::draw_path_ansi(&grid, &path));
+    } else {
+      println!("{}", render::draw_path(&grid, &path));
+    }
+    if let Some(file) = svg {
+      std::fs::write(file, render::to_svg(&grid, &path, 10)).unwrap();
+    }
+  }
 }
 
 #[cfg(test)]
@@ -165,4 +311,118 @@ abdefghi";
   fn part_two_example() {
     assert_eq!(part_two(INPUT), 29);
   }
+
+  #[test]
+  fn search_algorithms_agree() {
+    let grid = parse_hill_height_matrix(INPUT);
+    let (start, end) = find_start_and_end(&grid);
+    let path = shortest_path(&grid, &ClimbingPolicy::default(), &start, &end).unwrap();
+    assert_eq!(path.first(), Some(&start));
+    assert_eq!(path.last(), Some(&end));
+    assert!(path
+      .windows(2)
+      .all(|step| possible_moves(&grid, &ClimbingPolicy::default(), step[0]).contains(&step[1])));
+    for algorithm in ["dijkstra", "astar"] {
+      assert_eq!(
+        weighted_path_length(&grid, &ClimbingPolicy::default(), &start, &end, algorithm),
+        Some(31)
+      );
+    }
+  }
+
+  #[test]
+  fn draw_example_path() {
+    let grid = parse_hill_height_matrix(INPUT);
+    let (start, end) = find_start_and_end(&grid);
+    let path = shortest_path(&grid, &ClimbingPolicy::default(), &start, &end).unwrap();
+    assert_eq!(
+      render::draw_path(&grid, &path),
+      "v..v<<<<
+>v.vv<<^
+.v.v>E^^
+.>v>>>^^
+..>>>>>^"
+    );
+  }
+
+  #[test]
+  fn start_and_end_elevations() {
+    let policy = ClimbingPolicy::default();
+    assert!(policy.legal_move('S', 'b'));
+    assert!(!policy.legal_move('S', 'c'));
+    assert!(policy.legal_move('x', 'E') == policy.legal_move('x', 'z'));
+    assert!(policy.legal_move('E', 'a'));
+    let grid = parse_hill_height_matrix("SbcdefghijklmnopqrstuvwxyE");
+    assert_eq!(fewest_steps_from_start(&grid, &policy), Some(25));
+    let grid = parse_hill_height_matrix("Sz\nyE");
+    assert_eq!(fewest_steps_from_start(&grid, &policy), None);
+  }
+
+  #[test]
+  fn climbing_policies() {
+    let grid = parse_hill_height_matrix(INPUT);
+    let steep = ClimbingPolicy {
+      max_ascent: 25,
+      ..ClimbingPolicy::default()
+    };
+    assert_eq!(fewest_steps_from_start(&grid, &steep), Some(7));
+    let diagonal = ClimbingPolicy {
+      diagonal: true,
+      ..ClimbingPolicy::default()
+    };
+    let diagonal_steps = fewest_steps_from_start(&grid, &diagonal).unwrap();
+    assert!(diagonal_steps < 31);
+    assert_eq!(
+      weighted_path_length(&grid, &diagonal, &(0, 0), &(2, 5), "astar"),
+      Some(diagonal_steps)
+    );
+    let flat = ClimbingPolicy {
+      max_descent: 0,
+      ..ClimbingPolicy::default()
+    };
+    assert_eq!(fewest_steps_from_start(&grid, &flat), Some(31));
+  }
+
+  #[test]
+  fn summit_distance_field() {
+    let grid = parse_hill_height_matrix(INPUT);
+    let field = distance_field(&grid, &ClimbingPolicy::default());
+    assert_eq!(field[0][0], Some(31));
+    assert_eq!(field[2][5], Some(0));
+    let lowest = find_character_positions(&grid, 'a')
+      .into_iter()
+      .filter_map(|(i, j)| field[i][j])
+      .min();
+    assert_eq!(lowest, Some(29));
+    let (_, end) = find_start_and_end(&grid);
+    let steep = ClimbingPolicy {
+      max_ascent: 2,
+      ..ClimbingPolicy::default()
+    };
+    let diagonal = ClimbingPolicy {
+      diagonal: true,
+      ..ClimbingPolicy::default()
+    };
+    for policy in [ClimbingPolicy::default(), steep, diagonal] {
+      let brute_force = (0..grid.len())
+        .flat_map(|i| (0..grid[i].len()).map(move |j| (i, j)))
+        .filter(|&(i, j)| elevation(grid[i][j]) == b'a')
+        .filter_map(|start| shortest_path_length(&grid, &policy, &start, &end))
+        .min();
+      assert_eq!(fewest_steps_from_lowest(&grid, &policy), brute_force);
+    }
+    assert_eq!(
+      fewest_steps_from_lowest(&grid, &ClimbingPolicy::default()),
+      Some(29)
+    );
+    assert_eq!(
+      format_distance_field(&field)
+        .lines()
+        .next()
+        .unwrap()
+        .split_whitespace()
+        .count(),
+      8
+    );
+  }
 }
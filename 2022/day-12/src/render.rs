@@ -0,0 +1,132 @@
+use crate::{elevation, Position};
+use std::collections::HashMap;
+
+fn arrow(from: Position, to: Position) -> char {
+  match (to.0 as i64 - from.0 as i64, to.1 as i64 - from.1 as i64) {
+    (-1, 0) => '^',
+    (1, 0) => 'v',
+    (0, -1) => '<',
+    (0, 1) => '>',
+    (-1, -1) => '↖',
+    (-1, 1) => '↗',
+    (1, -1) => '↙',
+    (1, 1) => '↘',
+    _ => '*',
+  }
+}
+
+fn path_arrows(path: &[Position]) -> HashMap<Position, char> {
+  let mut arrows: HashMap<Position, char> = path
+    .windows(2)
+    .map(|step| (step[0], arrow(step[0], step[1])))
+    .collect();
+  if let Some(&end) = path.last() {
+    arrows.insert(end, 'E');
+  }
+  arrows
+}
+
+fn elevation_color(height: char) -> (u8, u8, u8) {
+  let level = u32::from(elevation(height).clamp(b'a', b'z') - b'a');
+  let red = 40 + level * 8;
+  let green = 120 + level * 5;
+  let blue = 40 + level * 7;
+  (red as u8, green as u8, blue as u8)
+}
+
+pub fn draw_path(grid: &[Vec<char>], path: &[Position]) -> String {
+  let arrows = path_arrows(path);
+  grid
+    .iter()
+    .enumerate()
+    .map(|(i, row)| {
+      (0..row.len())
+        .map(|j| arrows.get(&(i, j)).copied().unwrap_or('.'))
+        .collect::<String>()
+    })
+    .collect::<Vec<String>>()
+    .join("\n")
+}
+
+pub fn draw_path_ansi(grid: &[Vec<char>], path: &[Position]) -> String {
+  let arrows = path_arrows(path);
+  grid
+    .iter()
+    .enumerate()
+    .map(|(i, row)| {
+      let mut line: String = row
+        .iter()
+        .enumerate()
+        .map(|(j, &height)| {
+          let (red, green, blue) = elevation_color(height);
+          let (symbol, style) = match arrows.get(&(i, j)) {
+            Some(&arrow) => (arrow, "1;97"),
+            None => (height, "30"),
+          };
+          format!("\x1b[{};48;2;{};{};{}m{}", style, red, green, blue, symbol)
+        })
+        .collect();
+      line.push_str("\x1b[0m");
+      line
+    })
+    .collect::<Vec<String>>()
+    .join("\n")
+}
+
+pub fn to_svg(grid: &[Vec<char>], path: &[Position], cell: usize) -> String {
+  let height = grid.len() * cell;
+  let width = grid.iter().map(Vec::len).max().unwrap_or(0) * cell;
+  let mut svg = format!(
+    "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">\n",
+    width, height
+  );
+  for (i, row) in grid.iter().enumerate() {
+    for (j, &c) in row.iter().enumerate() {
+      let (red, green, blue) = elevation_color(c);
+      svg.push_str(&format!(
+        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"rgb({},{},{})\"/>\n",
+        j * cell,
+        i * cell,
+        cell,
+        cell,
+        red,
+        green,
+        blue
+      ));
+    }
+  }
+  let points: Vec<String> = path
+    .iter()
+    .map(|(i, j)| format!("{},{}", j * cell + cell / 2, i * cell + cell / 2))
+    .collect();
+  svg.push_str(&format!(
+    "<polyline points=\"{}\" fill=\"none\" stroke=\"red\" stroke-width=\"{}\"/>\n",
+    points.join(" "),
+    (cell / 4).max(1)
+  ));
+  svg.push_str("</svg>\n");
+  svg
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn arrows_follow_path() {
+    let grid = vec![vec!['a'; 3]; 2];
+    let path = [(0, 0), (0, 1), (1, 1), (1, 0)];
+    assert_eq!(draw_path(&grid, &path), ">v.\nE<.");
+    let svg = to_svg(&grid, &path, 10);
+    assert!(svg.contains("width=\"30\" height=\"20\""));
+    assert!(svg.contains("points=\"5,5 15,5 15,15 5,15\""));
+    assert_eq!(draw_path_ansi(&grid, &path).matches("\x1b[1;97").count(), 4);
+  }
+
+  #[test]
+  fn colors_unexpected_heights() {
+    assert_eq!(elevation_color('#'), elevation_color('a'));
+    assert_eq!(elevation_color('~'), elevation_color('z'));
+    assert_eq!(elevation_color('E'), (240, 245, 215));
+  }
+}
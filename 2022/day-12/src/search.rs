@@ -0,0 +1,135 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::hash::Hash;
+
+fn reconstruct_path<N: Clone + Eq + Hash>(parents: &HashMap<N, Option<N>>, goal: N) -> Vec<N> {
+  let mut path = vec![goal];
+  while let Some(Some(parent)) = parents.get(path.last().unwrap()) {
+    path.push(parent.clone());
+  }
+  path.reverse();
+  path
+}
+
+pub fn bfs<N, I>(
+  start: N,
+  mut neighbours: impl FnMut(&N) -> I,
+  mut is_goal: impl FnMut(&N) -> bool,
+) -> Option<Vec<N>>
+where
+  N: Clone + Eq + Hash,
+  I: IntoIterator<Item = N>,
+{
+  let mut parents: HashMap<N, Option<N>> = HashMap::from([(start.clone(), None)]);
+  let mut queue = VecDeque::from([start]);
+  while let Some(node) = queue.pop_front() {
+    if is_goal(&node) {
+      return Some(reconstruct_path(&parents, node));
+    }
+    for next in neighbours(&node) {
+      if !parents.contains_key(&next) {
+        parents.insert(next.clone(), Some(node.clone()));
+        queue.push_back(next);
+      }
+    }
+  }
+  None
+}
+
+pub fn bfs_distances<N, I>(start: N, mut neighbours: impl FnMut(&N) -> I) -> HashMap<N, usize>
+where
+  N: Clone + Eq + Hash,
+  I: IntoIterator<Item = N>,
+{
+  let mut distances: HashMap<N, usize> = HashMap::from([(start.clone(), 0)]);
+  let mut queue = VecDeque::from([start]);
+  while let Some(node) = queue.pop_front() {
+    let distance = distances[&node];
+    for next in neighbours(&node) {
+      if !distances.contains_key(&next) {
+        distances.insert(next.clone(), distance + 1);
+        queue.push_back(next);
+      }
+    }
+  }
+  distances
+}
+
+pub fn astar<N, I>(
+  start: N,
+  mut neighbours: impl FnMut(&N) -> I,
+  mut heuristic: impl FnMut(&N) -> u32,
+  mut is_goal: impl FnMut(&N) -> bool,
+) -> Option<(Vec<N>, u32)>
+where
+  N: Clone + Eq + Hash + Ord,
+  I: IntoIterator<Item = (N, u32)>,
+{
+  let mut parents: HashMap<N, Option<N>> = HashMap::from([(start.clone(), None)]);
+  let mut costs: HashMap<N, u32> = HashMap::from([(start.clone(), 0)]);
+  let mut heap = BinaryHeap::from([Reverse((heuristic(&start), 0, start))]);
+  while let Some(Reverse((_, cost, node))) = heap.pop() {
+    if cost > costs[&node] {
+      continue;
+    }
+    if is_goal(&node) {
+      return Some((reconstruct_path(&parents, node), cost));
+    }
+    for (next, step) in neighbours(&node) {
+      let next_cost = cost + step;
+      if costs.get(&next).is_none_or(|&known| next_cost < known) {
+        costs.insert(next.clone(), next_cost);
+        parents.insert(next.clone(), Some(node.clone()));
+        heap.push(Reverse((next_cost + heuristic(&next), next_cost, next)));
+      }
+    }
+  }
+  None
+}
+
+pub fn dijkstra<N, I>(
+  start: N,
+  neighbours: impl FnMut(&N) -> I,
+  is_goal: impl FnMut(&N) -> bool,
+) -> Option<(Vec<N>, u32)>
+where
+  N: Clone + Eq + Hash + Ord,
+  I: IntoIterator<Item = (N, u32)>,
+{
+  astar(start, neighbours, |_| 0, is_goal)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn edges(node: &u32) -> Vec<(u32, u32)> {
+    match node {
+      0 => vec![(1, 7), (2, 1)],
+      1 => vec![(4, 1)],
+      2 => vec![(3, 1)],
+      3 => vec![(1, 1), (4, 9)],
+      _ => vec![],
+    }
+  }
+
+  #[test]
+  fn unweighted_search() {
+    let neighbours = |node: &u32| edges(node).into_iter().map(|(next, _)| next);
+    assert_eq!(bfs(0, neighbours, |&node| node == 4), Some(vec![0, 1, 4]));
+    assert_eq!(bfs(4, neighbours, |&node| node == 0), None);
+    let distances = bfs_distances(0, neighbours);
+    assert_eq!((distances[&3], distances[&4]), (2, 2));
+  }
+
+  #[test]
+  fn weighted_search() {
+    let expected = Some((vec![0, 2, 3, 1, 4], 4));
+    assert_eq!(dijkstra(0, edges, |&node| node == 4), expected);
+    assert_eq!(
+      astar(0, edges, |&node| u32::from(node != 4), |&node| node == 4),
+      expected
+    );
+    assert_eq!(dijkstra(1, edges, |&node| node == 0), None);
+  }
+}
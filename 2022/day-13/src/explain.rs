@@ -0,0 +1,88 @@
+use std::cmp::Ordering;
+
+use crate::packet::List;
+
+fn line(lines: &mut Vec<String>, depth: usize, text: String) {
+  lines.push(format!("{}- {}", "  ".repeat(depth), text));
+}
+
+fn verdict(ordering: Ordering) -> &'static str {
+  match ordering {
+    Ordering::Less => "so inputs are in the right order",
+    _ => "so inputs are not in the right order",
+  }
+}
+
+fn explain_at(left: &List, right: &List, depth: usize, lines: &mut Vec<String>) -> Ordering {
+  line(lines, depth, format!("Compare {} vs {}", left, right));
+  match (left, right) {
+    (List::Value(a), List::Value(b)) => {
+      let ordering = a.cmp(b);
+      let side = match ordering {
+        Ordering::Less => "Left",
+        Ordering::Greater => "Right",
+        Ordering::Equal => return ordering,
+      };
+      line(
+        lines,
+        depth + 1,
+        format!("{} side is smaller, {}", side, verdict(ordering)),
+      );
+      ordering
+    }
+    (List::Value(a), List::Nested(_)) => {
+      let promoted = List::Nested(vec![List::Value(*a)]);
+      line(
+        lines,
+        depth + 1,
+        format!(
+          "Mixed types; convert left to {} and retry comparison",
+          promoted
+        ),
+      );
+      explain_at(&promoted, right, depth + 1, lines)
+    }
+    (List::Nested(_), List::Value(b)) => {
+      let promoted = List::Nested(vec![List::Value(*b)]);
+      line(
+        lines,
+        depth + 1,
+        format!(
+          "Mixed types; convert right to {} and retry comparison",
+          promoted
+        ),
+      );
+      explain_at(left, &promoted, depth + 1, lines)
+    }
+    (List::Nested(a), List::Nested(b)) => {
+      for (l, r) in a.iter().zip(b) {
+        let ordering = explain_at(l, r, depth + 1, lines);
+        if ordering != Ordering::Equal {
+          return ordering;
+        }
+      }
+      let ordering = a.len().cmp(&b.len());
+      let side = match ordering {
+        Ordering::Less => "Left",
+        Ordering::Greater => "Right",
+        Ordering::Equal => return ordering,
+      };
+      line(
+        lines,
+        depth + 1,
+        format!("{} side ran out of items, {}", side, verdict(ordering)),
+      );
+      ordering
+    }
+  }
+}
+
+pub fn explain(left: &List, right: &List) -> (Ordering, String) {
+  let mut lines = Vec::new();
+  let ordering = explain_at(left, right, 0, &mut lines);
+  (ordering, lines.join("\n"))
+}
+
+pub fn explain_pair(index: usize, left: &List, right: &List) -> String {
+  format!("== Pair {} ==\n{}", index, explain(left, right).1)
+}
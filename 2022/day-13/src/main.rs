@@ -1,60 +1,35 @@
+mod explain;
+mod packet;
+mod sorter;
+
 use std::cmp::Ordering;
 
-use nom::branch::alt;
-use nom::bytes::complete::tag;
-use nom::character::complete::{newline, u32};
-use nom::multi::{separated_list0, separated_list1};
-use nom::sequence::{delimited, pair, separated_pair};
+use nom::character::complete::newline;
+use nom::multi::separated_list1;
+use nom::sequence::{pair, separated_pair};
+use nom::IResult;
 use nom::Parser;
-use nom::{combinator::map, IResult};
-
-#[derive(Debug, Clone, PartialEq)]
-enum List {
-  Value(u32),
-  Nested(Vec<List>),
-}
-
-impl Eq for List {}
+use packet::{list, parse_list, List, PacketError};
+use serde_json::Value;
 
-impl PartialOrd for List {
-  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-    Some(self.cmp(&other))
-  }
+fn parse_pairs(input: &str) -> IResult<&str, Vec<(List, List)>> {
+  separated_list1(pair(newline, newline), separated_pair(list, newline, list)).parse(input)
 }
 
-impl Ord for List {
-  fn cmp(&self, other: &Self) -> Ordering {
-    match (self, other) {
-      (List::Value(a), List::Value(b)) => a.cmp(b),
-      (List::Value(a), List::Nested(_)) => List::Nested(vec![List::Value(*a)]).cmp(other),
-      (List::Nested(_), List::Value(b)) => self.cmp(&List::Nested(vec![List::Value(*b)])),
-      (List::Nested(a), List::Nested(b)) => a.cmp(b),
+fn load_pairs(input: &str) -> Result<Vec<(List, List)>, PacketError> {
+  let input = input.trim_end();
+  match parse_pairs(input) {
+    Ok(("", pairs)) => Ok(pairs),
+    Ok((rest, _)) => Err(PacketError::TrailingInput(rest.trim_start().to_string())),
+    Err(nom::Err::Error(err) | nom::Err::Failure(err)) => {
+      Err(PacketError::Syntax(err.input.to_string()))
     }
+    Err(nom::Err::Incomplete(_)) => Err(PacketError::Syntax(input.to_string())),
   }
 }
 
-fn list(input: &str) -> IResult<&str, List> {
-  alt((
-    map(
-      delimited(tag("["), separated_list0(tag(","), list), tag("]")),
-      |l| List::Nested(l),
-    ),
-    map(u32, |n| List::Value(n)),
-  ))
-  .parse(input)
-}
-
-fn parse_list(input: &str) -> List {
-  let (_, list) = list(input).unwrap();
-  return list;
-}
-
-fn parse_pairs(input: &str) -> IResult<&str, Vec<(List, List)>> {
-  separated_list1(pair(newline, newline), separated_pair(list, newline, list)).parse(input)
-}
-
 fn part_one(input: &str) -> usize {
-  let (_, pairs) = parse_pairs(input).unwrap();
+  let pairs = load_pairs(input).unwrap();
   pairs
     .iter()
     .enumerate()
@@ -65,29 +40,110 @@ fn part_one(input: &str) -> usize {
     .sum()
 }
 
+fn parse_dividers(dividers: &str) -> Result<Vec<List>, PacketError> {
+  dividers.split(';').map(parse_list).collect()
+}
+
 fn part_two(input: &str) -> usize {
-  let (_, pairs) = parse_pairs(input).unwrap();
-  let divider_packet_a = parse_list("[[2]]");
-  let divider_packet_b = parse_list("[[6]]");
-  let mut lists: Vec<&List> = pairs
+  let dividers = parse_dividers("[[2]];[[6]]").unwrap();
+  sorter::divider_positions(input.as_bytes(), &dividers)
+    .unwrap()
     .iter()
-    .flat_map(|(l, r)| [l, r])
-    .chain([&divider_packet_a, &divider_packet_b])
-    .collect();
-  lists.sort();
-  lists
-    .iter()
-    .enumerate()
-    .filter(|(_, &list)| list == &divider_packet_a || list == &divider_packet_b)
-    .map(|(i, _)| i + 1)
     .product()
 }
 
+fn packets_to_json(input: &str) -> Result<Value, PacketError> {
+  input
+    .lines()
+    .filter(|line| !line.trim().is_empty())
+    .map(|line| parse_list(line).map(|packet| Value::from(&packet)))
+    .collect()
+}
+
+fn packets_from_json(json: &Value) -> Result<String, PacketError> {
+  let packets = match json {
+    Value::Array(packets) => packets
+      .iter()
+      .map(List::try_from)
+      .collect::<Result<Vec<List>, _>>()?,
+    _ => return Err(PacketError::InvalidJson(json.to_string())),
+  };
+  Ok(
+    packets
+      .chunks(2)
+      .map(|pair| {
+        pair
+          .iter()
+          .map(List::to_string)
+          .collect::<Vec<String>>()
+          .join("\n")
+      })
+      .collect::<Vec<String>>()
+      .join("\n\n"),
+  )
+}
+
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+  let pos = args.iter().position(|arg| arg == name)?;
+  args.get(pos + 1).map(String::as_str)
+}
+
 fn main() {
   let input = include_str!("input");
   println!("{}", part_one(input));
   println!();
   println!("{}", part_two(input));
+
+  let args: Vec<String> = std::env::args().skip(1).collect();
+  if args.iter().any(|arg| arg == "--to-json") {
+    println!();
+    match packets_to_json(input) {
+      Ok(json) => println!("{}", json),
+      Err(err) => println!("{}", err),
+    }
+  }
+  if let Some(pair) = option_value(&args, "--explain") {
+    let pairs = match load_pairs(input) {
+      Ok(pairs) => pairs,
+      Err(err) => {
+        println!();
+        println!("{}", err);
+        Vec::new()
+      }
+    };
+    let selected: Vec<usize> = match pair {
+      "all" => (1..=pairs.len()).collect(),
+      index => vec![index.parse().unwrap()],
+    };
+    for index in selected {
+      let (left, right) = &pairs[index - 1];
+      println!();
+      println!("{}", explain::explain_pair(index, left, right));
+    }
+  }
+  if let Some(dividers) = option_value(&args, "--dividers") {
+    let dividers = parse_dividers(dividers).unwrap();
+    let positions = match option_value(&args, "--stream") {
+      Some(file) => {
+        let reader = std::io::BufReader::new(std::fs::File::open(file).unwrap());
+        sorter::divider_positions(reader, &dividers)
+      }
+      None => sorter::divider_positions(input.as_bytes(), &dividers),
+    };
+    println!();
+    match positions {
+      Ok(positions) => println!("{:?}", positions),
+      Err((line, err)) => println!("line {}: {}", line, err),
+    }
+  }
+  if let Some(file) = option_value(&args, "--from-json") {
+    let json: Value = serde_json::from_str(&std::fs::read_to_string(file).unwrap()).unwrap();
+    println!();
+    match packets_from_json(&json) {
+      Ok(packets) => println!("{}", packets),
+      Err(err) => println!("{}", err),
+    }
+  }
 }
 
 #[cfg(test)]
@@ -139,12 +195,12 @@ mod test {
       List::Value(1),
     ]);
 
-    assert_eq!(left < right, true);
+    assert!(left < right);
 
-    let left = parse_list("[1,[2,[3,[4,[5,6,7]]]],8,9]");
-    let right = parse_list("[1,[2,[3,[4,[5,6,0]]]],8,9]");
+    let left = parse_list("[1,[2,[3,[4,[5,6,7]]]],8,9]").unwrap();
+    let right = parse_list("[1,[2,[3,[4,[5,6,0]]]],8,9]").unwrap();
 
-    assert_eq!(left < right, false);
+    assert!(left >= right);
   }
 
   #[test]
@@ -157,7 +213,7 @@ mod test {
       List::Value(1),
       List::Value(1),
     ]);
-    let output = parse_list(input);
+    let output = parse_list(input).unwrap();
     assert_eq!(output, expected_output);
 
     let input = "[1,[2,[3,[4,[5,6,7]]]],8,9]";
@@ -177,7 +233,7 @@ mod test {
       List::Value(9),
     ]);
 
-    let output = parse_list(input);
+    let output = parse_list(input).unwrap();
     assert_eq!(output, expected_output);
   }
 
@@ -191,6 +247,109 @@ mod test {
     let v = vec![1, 1, 3, 1];
     let w = vec![1, 1, 5, 1, 1];
     dbg!(v.cmp(&w));
-    assert_eq!(v < w, true);
+    assert!(v < w);
+  }
+
+  #[test]
+  fn json_round_trip() {
+    let json = packets_to_json(INPUT).unwrap();
+    assert_eq!(json.as_array().map(Vec::len), Some(16));
+    assert_eq!(packets_from_json(&json), Ok(INPUT.to_string()));
+  }
+
+  #[test]
+  fn strict_pair_loading() {
+    assert_eq!(load_pairs(&format!("{}\n", INPUT)).map(|p| p.len()), Ok(8));
+    assert_eq!(
+      load_pairs("[1]\n[2]\n\n[3]\n[4]]"),
+      Err(PacketError::TrailingInput("]".to_string()))
+    );
+    assert_eq!(
+      load_pairs("[1]\n[2]\n\n[3]"),
+      Err(PacketError::TrailingInput("[3]".to_string()))
+    );
+    assert_eq!(
+      load_pairs("[1,x]\n[2]"),
+      Err(PacketError::Syntax("[1,x]\n[2]".to_string()))
+    );
   }
+
+  #[test]
+  fn explain_example() {
+    let pairs = load_pairs(INPUT).unwrap();
+    let trace: Vec<String> = pairs
+      .iter()
+      .enumerate()
+      .map(|(i, (left, right))| explain::explain_pair(i + 1, left, right))
+      .collect();
+    assert_eq!(trace.join("\n\n"), EXPLAINED);
+    for (left, right) in &pairs {
+      assert_eq!(explain::explain(left, right).0, left.cmp(right));
+    }
+  }
+
+  const EXPLAINED: &str = "== Pair 1 ==
+- Compare [1,1,3,1,1] vs [1,1,5,1,1]
+  - Compare 1 vs 1
+  - Compare 1 vs 1
+  - Compare 3 vs 5
+    - Left side is smaller, so inputs are in the right order
+
+== Pair 2 ==
+- Compare [[1],[2,3,4]] vs [[1],4]
+  - Compare [1] vs [1]
+    - Compare 1 vs 1
+  - Compare [2,3,4] vs 4
+    - Mixed types; convert right to [4] and retry comparison
+    - Compare [2,3,4] vs [4]
+      - Compare 2 vs 4
+        - Left side is smaller, so inputs are in the right order
+
+== Pair 3 ==
+- Compare [9] vs [[8,7,6]]
+  - Compare 9 vs [8,7,6]
+    - Mixed types; convert left to [9] and retry comparison
+    - Compare [9] vs [8,7,6]
+      - Compare 9 vs 8
+        - Right side is smaller, so inputs are not in the right order
+
+== Pair 4 ==
+- Compare [[4,4],4,4] vs [[4,4],4,4,4]
+  - Compare [4,4] vs [4,4]
+    - Compare 4 vs 4
+    - Compare 4 vs 4
+  - Compare 4 vs 4
+  - Compare 4 vs 4
+  - Left side ran out of items, so inputs are in the right order
+
+== Pair 5 ==
+- Compare [7,7,7,7] vs [7,7,7]
+  - Compare 7 vs 7
+  - Compare 7 vs 7
+  - Compare 7 vs 7
+  - Right side ran out of items, so inputs are not in the right order
+
+== Pair 6 ==
+- Compare [] vs [3]
+  - Left side ran out of items, so inputs are in the right order
+
+== Pair 7 ==
+- Compare [[[]]] vs [[]]
+  - Compare [[]] vs []
+    - Right side ran out of items, so inputs are not in the right order
+
+== Pair 8 ==
+- Compare [1,[2,[3,[4,[5,6,7]]]],8,9] vs [1,[2,[3,[4,[5,6,0]]]],8,9]
+  - Compare 1 vs 1
+  - Compare [2,[3,[4,[5,6,7]]]] vs [2,[3,[4,[5,6,0]]]]
+    - Compare 2 vs 2
+    - Compare [3,[4,[5,6,7]]] vs [3,[4,[5,6,0]]]
+      - Compare 3 vs 3
+      - Compare [4,[5,6,7]] vs [4,[5,6,0]]
+        - Compare 4 vs 4
+        - Compare [5,6,7] vs [5,6,0]
+          - Compare 5 vs 5
+          - Compare 6 vs 6
+          - Compare 7 vs 0
+            - Right side is smaller, so inputs are not in the right order";
 }
@@ -0,0 +1,172 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use nom::branch::alt;
+use nom::bytes::complete::tag;
+use nom::character::complete::u32;
+use nom::multi::separated_list0;
+use nom::sequence::delimited;
+use nom::Parser;
+use nom::{combinator::map, IResult};
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum List {
+  Value(u32),
+  Nested(Vec<List>),
+}
+
+impl Eq for List {}
+
+impl PartialOrd for List {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Ord for List {
+  fn cmp(&self, other: &Self) -> Ordering {
+    match (self, other) {
+      (List::Value(a), List::Value(b)) => a.cmp(b),
+      (List::Value(a), List::Nested(_)) => List::Nested(vec![List::Value(*a)]).cmp(other),
+      (List::Nested(_), List::Value(b)) => self.cmp(&List::Nested(vec![List::Value(*b)])),
+      (List::Nested(a), List::Nested(b)) => a.cmp(b),
+    }
+  }
+}
+
+impl fmt::Display for List {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      List::Value(n) => write!(f, "{}", n),
+      List::Nested(items) => {
+        write!(f, "[")?;
+        for (i, item) in items.iter().enumerate() {
+          if i > 0 {
+            write!(f, ",")?;
+          }
+          write!(f, "{}", item)?;
+        }
+        write!(f, "]")
+      }
+    }
+  }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PacketError {
+  Syntax(String),
+  TrailingInput(String),
+  InvalidJson(String),
+}
+
+impl fmt::Display for PacketError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      PacketError::Syntax(rest) => write!(f, "invalid packet at `{}`", rest),
+      PacketError::TrailingInput(rest) => write!(f, "unexpected trailing input `{}`", rest),
+      PacketError::InvalidJson(value) => write!(f, "`{}` is not a packet", value),
+    }
+  }
+}
+
+impl From<&List> for Value {
+  fn from(list: &List) -> Self {
+    match list {
+      List::Value(n) => Value::from(*n),
+      List::Nested(items) => Value::Array(items.iter().map(Value::from).collect()),
+    }
+  }
+}
+
+impl TryFrom<&Value> for List {
+  type Error = PacketError;
+  fn try_from(value: &Value) -> Result<Self, Self::Error> {
+    match value {
+      Value::Number(n) => n
+        .as_u64()
+        .and_then(|n| n.try_into().ok())
+        .map(List::Value)
+        .ok_or_else(|| PacketError::InvalidJson(value.to_string())),
+      Value::Array(items) => items
+        .iter()
+        .map(List::try_from)
+        .collect::<Result<_, _>>()
+        .map(List::Nested),
+      _ => Err(PacketError::InvalidJson(value.to_string())),
+    }
+  }
+}
+
+pub fn list(input: &str) -> IResult<&str, List> {
+  alt((
+    map(
+      delimited(tag("["), separated_list0(tag(","), list), tag("]")),
+      List::Nested,
+    ),
+    map(u32, List::Value),
+  ))
+  .parse(input)
+}
+
+pub fn parse_list(input: &str) -> Result<List, PacketError> {
+  let input = input.trim_end();
+  match list(input) {
+    Ok(("", list)) => Ok(list),
+    Ok((rest, _)) => Err(PacketError::TrailingInput(rest.to_string())),
+    Err(nom::Err::Error(err) | nom::Err::Failure(err)) => {
+      Err(PacketError::Syntax(err.input.to_string()))
+    }
+    Err(nom::Err::Incomplete(_)) => Err(PacketError::Syntax(input.to_string())),
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn display_round_trip() {
+    for packet in [
+      "[]",
+      "[[[]]]",
+      "[1,[2,[3,[4,[5,6,7]]]],8,9]",
+      "[[4,4],4,4,4]",
+    ] {
+      assert_eq!(parse_list(packet).unwrap().to_string(), packet);
+    }
+  }
+
+  #[test]
+  fn strict_parsing() {
+    assert_eq!(
+      parse_list("[1,2]\n"),
+      Ok(List::Nested(vec![List::Value(1), List::Value(2)]))
+    );
+    assert_eq!(
+      parse_list("[1,2]]"),
+      Err(PacketError::TrailingInput("]".to_string()))
+    );
+    assert_eq!(
+      parse_list("[1,2] [3]"),
+      Err(PacketError::TrailingInput(" [3]".to_string()))
+    );
+    assert!(matches!(parse_list("[1,"), Err(PacketError::Syntax(_))));
+    assert!(matches!(parse_list(""), Err(PacketError::Syntax(_))));
+  }
+
+  #[test]
+  fn json_interop() {
+    let packet = parse_list("[1,[2,[]],3]").unwrap();
+    let json: Value = serde_json::from_str("[1, [2, []], 3]").unwrap();
+    assert_eq!(Value::from(&packet), json);
+    assert_eq!(List::try_from(&json), Ok(packet));
+    let invalid: Value = serde_json::from_str("[1, \"two\"]").unwrap();
+    assert_eq!(
+      List::try_from(&invalid),
+      Err(PacketError::InvalidJson("\"two\"".to_string()))
+    );
+    let negative: Value = serde_json::from_str("[-1]").unwrap();
+    assert!(List::try_from(&negative).is_err());
+  }
+}
@@ -0,0 +1,255 @@
+use std::cmp::Ordering;
+use std::io::BufRead;
+
+use crate::packet::{List, PacketError};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Token {
+  Open,
+  Close,
+  Value(u32),
+}
+
+pub fn tokens(list: &List) -> Vec<Token> {
+  let mut tokens = Vec::new();
+  let mut stack: Vec<Result<&List, Token>> = vec![Ok(list)];
+  while let Some(next) = stack.pop() {
+    match next {
+      Ok(List::Value(n)) => tokens.push(Token::Value(*n)),
+      Ok(List::Nested(items)) => {
+        tokens.push(Token::Open);
+        stack.push(Err(Token::Close));
+        stack.extend(items.iter().rev().map(Ok));
+      }
+      Err(token) => tokens.push(token),
+    }
+  }
+  tokens
+}
+
+pub fn tokenize(input: &str) -> Result<Vec<Token>, PacketError> {
+  let input = input.trim_end();
+  let mut tokens = Vec::new();
+  let mut depth = 0usize;
+  let mut expect_value = true;
+  let mut rest = input;
+  while let Some(c) = rest.chars().next() {
+    if depth == 0 && !tokens.is_empty() {
+      return Err(PacketError::TrailingInput(rest.to_string()));
+    }
+    match c {
+      '[' if expect_value => {
+        tokens.push(Token::Open);
+        depth += 1;
+        rest = &rest[1..];
+        continue;
+      }
+      ']' if depth > 0 && (!expect_value || tokens.last() == Some(&Token::Open)) => {
+        tokens.push(Token::Close);
+        depth -= 1;
+        expect_value = false;
+        rest = &rest[1..];
+      }
+      ',' if depth > 0 && !expect_value => {
+        expect_value = true;
+        rest = &rest[1..];
+      }
+      '0'..='9' if expect_value => {
+        let digits = rest
+          .find(|c: char| !c.is_ascii_digit())
+          .unwrap_or(rest.len());
+        let value = rest[..digits]
+          .parse()
+          .map_err(|_| PacketError::Syntax(rest.to_string()))?;
+        tokens.push(Token::Value(value));
+        expect_value = false;
+        rest = &rest[digits..];
+      }
+      _ => return Err(PacketError::Syntax(rest.to_string())),
+    }
+  }
+  if depth > 0 || tokens.is_empty() {
+    return Err(PacketError::Syntax(rest.to_string()));
+  }
+  Ok(tokens)
+}
+
+struct TokenStream<'a> {
+  tokens: std::slice::Iter<'a, Token>,
+  injected: Vec<Token>,
+}
+
+impl Iterator for TokenStream<'_> {
+  type Item = Token;
+  fn next(&mut self) -> Option<Token> {
+    self.injected.pop().or_else(|| self.tokens.next().copied())
+  }
+}
+
+pub fn compare_tokens(left: &[Token], right: &[Token]) -> Ordering {
+  let mut left = TokenStream {
+    tokens: left.iter(),
+    injected: Vec::new(),
+  };
+  let mut right = TokenStream {
+    tokens: right.iter(),
+    injected: Vec::new(),
+  };
+  loop {
+    match (left.next(), right.next()) {
+      (None, None) => return Ordering::Equal,
+      (None, Some(_)) => return Ordering::Less,
+      (Some(_), None) => return Ordering::Greater,
+      (Some(Token::Value(a)), Some(Token::Value(b))) if a != b => return a.cmp(&b),
+      (Some(Token::Close), Some(Token::Close)) => {}
+      (Some(Token::Close), Some(_)) => return Ordering::Less,
+      (Some(_), Some(Token::Close)) => return Ordering::Greater,
+      (Some(Token::Value(a)), Some(Token::Open)) => {
+        left.injected.extend([Token::Close, Token::Value(a)])
+      }
+      (Some(Token::Open), Some(Token::Value(b))) => {
+        right.injected.extend([Token::Close, Token::Value(b)])
+      }
+      _ => {}
+    }
+  }
+}
+
+pub struct DividerCounter {
+  dividers: Vec<Vec<Token>>,
+  smaller: Vec<usize>,
+}
+
+impl DividerCounter {
+  pub fn new(dividers: &[List]) -> Self {
+    let dividers: Vec<Vec<Token>> = dividers.iter().map(tokens).collect();
+    let smaller = dividers
+      .iter()
+      .enumerate()
+      .map(|(i, divider)| {
+        dividers
+          .iter()
+          .enumerate()
+          .filter(|(j, other)| match compare_tokens(other, divider) {
+            Ordering::Less => true,
+            Ordering::Equal => *j < i,
+            Ordering::Greater => false,
+          })
+          .count()
+      })
+      .collect();
+    Self { dividers, smaller }
+  }
+  pub fn observe(&mut self, packet: &[Token]) {
+    for (divider, smaller) in self.dividers.iter().zip(self.smaller.iter_mut()) {
+      if compare_tokens(packet, divider) == Ordering::Less {
+        *smaller += 1;
+      }
+    }
+  }
+  pub fn positions(&self) -> Vec<usize> {
+    self.smaller.iter().map(|smaller| smaller + 1).collect()
+  }
+}
+
+pub fn divider_positions<R: BufRead>(
+  reader: R,
+  dividers: &[List],
+) -> Result<Vec<usize>, (usize, PacketError)> {
+  let mut counter = DividerCounter::new(dividers);
+  for (index, line) in reader.lines().enumerate() {
+    let line = line.map_err(|err| (index + 1, PacketError::Syntax(err.to_string())))?;
+    if line.trim().is_empty() {
+      continue;
+    }
+    let packet = tokenize(&line).map_err(|err| (index + 1, err))?;
+    counter.observe(&packet);
+  }
+  Ok(counter.positions())
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::packet::parse_list;
+
+  #[test]
+  fn token_comparison_matches_list_order() {
+    let packets = [
+      "[]",
+      "[[]]",
+      "[[[]]]",
+      "[1,1,3,1,1]",
+      "[1,1,5,1,1]",
+      "[[1],[2,3,4]]",
+      "[[1],4]",
+      "[9]",
+      "[[8,7,6]]",
+      "[[4,4],4,4]",
+      "[[4,4],4,4,4]",
+      "[7,7,7,7]",
+      "[7,7,7]",
+      "[3]",
+      "[[2]]",
+      "[2]",
+      "[[2],[]]",
+    ];
+    for left in packets {
+      for right in packets {
+        let (l, r) = (parse_list(left).unwrap(), parse_list(right).unwrap());
+        assert_eq!(
+          compare_tokens(&tokenize(left).unwrap(), &tokenize(right).unwrap()),
+          l.cmp(&r),
+          "{} vs {}",
+          left,
+          right
+        );
+        assert_eq!(tokenize(left).unwrap(), tokens(&l));
+      }
+    }
+  }
+
+  #[test]
+  fn strict_tokenizer() {
+    assert_eq!(
+      tokenize("[1]]"),
+      Err(PacketError::TrailingInput("]".to_string()))
+    );
+    for invalid in ["", "[1,]", "[,1]", "[1 2]", "[[1]", "]", "[1,,2]"] {
+      assert!(
+        matches!(tokenize(invalid), Err(PacketError::Syntax(_))),
+        "{}",
+        invalid
+      );
+    }
+  }
+
+  #[test]
+  fn deeply_nested_packets() {
+    let depth = 1_000_000;
+    let deep = format!("{}5{}", "[".repeat(depth), "]".repeat(depth));
+    let deeper = format!("{}6{}", "[".repeat(depth + 1), "]".repeat(depth + 1));
+    let (deep, deeper) = (tokenize(&deep).unwrap(), tokenize(&deeper).unwrap());
+    assert_eq!(compare_tokens(&deep, &deeper), Ordering::Less);
+    assert_eq!(compare_tokens(&deeper, &deep), Ordering::Greater);
+    assert_eq!(compare_tokens(&deep, &[Token::Value(5)]), Ordering::Equal);
+  }
+
+  #[test]
+  fn custom_dividers() {
+    let dividers = [
+      parse_list("[[6]]").unwrap(),
+      parse_list("[[2]]").unwrap(),
+      parse_list("[[2]]").unwrap(),
+    ];
+    let packets = "[1]\n\n[[3]]\n[7]\n[[2]]\n";
+    assert_eq!(
+      divider_positions(packets.as_bytes(), &dividers),
+      Ok(vec![6, 2, 3])
+    );
+    assert_eq!(
+      divider_positions("[1]\n[2".as_bytes(), &dividers),
+      Err((2, PacketError::Syntax("".to_string())))
+    );
+  }
+}
@@ -1,22 +1,25 @@
 use itertools::Either;
 use nom::bytes::complete::tag;
-use nom::character::complete::{newline, usize};
+use nom::character::complete::{i64, newline};
 use nom::multi::separated_list1;
 use nom::sequence::separated_pair;
 use nom::IResult;
 use nom::Parser;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::Write;
 
-type PathPoint = (usize, usize);
+type PathPoint = (i64, i64);
 
 fn rock_structures(input: &str) -> IResult<&str, Vec<Vec<PathPoint>>> {
   separated_list1(
     newline,
-    separated_list1(tag(" -> "), separated_pair(usize, tag(","), usize)),
+    separated_list1(tag(" -> "), separated_pair(i64, tag(","), i64)),
   )
   .parse(input)
 }
 
-fn fill_structure(structure: &Vec<PathPoint>) -> Vec<PathPoint> {
+fn fill_structure(structure: &[PathPoint]) -> Vec<PathPoint> {
   structure
     .windows(2)
     .flat_map(|w| {
@@ -29,103 +32,379 @@ fn fill_structure(structure: &Vec<PathPoint>) -> Vec<PathPoint> {
         Either::Right((start..=end).map(move |x| (x, a_y)))
       }
     })
+    .chain(structure.first().copied())
     .collect()
 }
 
-fn make_grid(input: &str, with_bottom: bool) -> Vec<Vec<bool>> {
-  let (_, structures) = rock_structures(input).unwrap();
-  let max_x = structures
-    .iter()
-    .flat_map(|l| l.iter().map(|point| point.0))
-    .max()
-    .expect("no max x?");
-  let max_y = structures
-    .iter()
-    .flat_map(|l| l.iter().map(|point| point.1))
-    .max()
-    .expect("no max y?");
-  let mut grid = vec![vec![false; max_y + 1]; max_x + 1];
-  if with_bottom {
-    grid = vec![vec![false; max_y + 1 + 2]; (max_x + 1) * 2];
-    for i in 0..(max_x + 1) * 2 {
-      grid[i][max_y + 2] = true;
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Cell {
+  Air,
+  Rock,
+  Sand,
+  Source,
+}
+
+impl Cell {
+  fn symbol(self) -> char {
+    match self {
+      Cell::Air => '.',
+      Cell::Rock => '#',
+      Cell::Sand => 'o',
+      Cell::Source => '+',
     }
   }
-  structures
-    .iter()
-    .flat_map(|s| fill_structure(s))
-    .for_each(|(x, y)| grid[x][y] = true);
-  return grid;
+  fn from_symbol(symbol: char) -> Self {
+    match symbol {
+      '#' => Cell::Rock,
+      'o' => Cell::Sand,
+      '+' => Cell::Source,
+      _ => Cell::Air,
+    }
+  }
+  fn color(self) -> [u8; 3] {
+    match self {
+      Cell::Air => [0x1e, 0x1e, 0x28],
+      Cell::Rock => [0x80, 0x80, 0x80],
+      Cell::Sand => [0xe6, 0xc2, 0x5a],
+      Cell::Source => [0xd0, 0x30, 0x30],
+    }
+  }
+}
+
+#[derive(Debug, PartialEq)]
+enum CaveError {
+  Parse(String),
+  NoSources,
+}
+
+impl fmt::Display for CaveError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      CaveError::Parse(line) => write!(f, "could not parse rock path at `{}`", line),
+      CaveError::NoSources => write!(f, "cave needs at least one sand source"),
+    }
+  }
+}
+
+fn parse_structures(input: &str) -> Result<Vec<Vec<PathPoint>>, CaveError> {
+  let input = input.trim_end();
+  let unparsed = |rest: &str| {
+    let offset = input.len() - rest.trim_start().len();
+    let line_start = input[..offset].rfind('\n').map_or(0, |newline| newline + 1);
+    CaveError::Parse(input[line_start..].lines().next().unwrap_or("").to_string())
+  };
+  if input.trim().is_empty() {
+    return Ok(Vec::new());
+  }
+  match rock_structures(input) {
+    Ok(("", structures)) => Ok(structures),
+    Ok((rest, _)) => Err(unparsed(rest)),
+    Err(nom::Err::Error(err) | nom::Err::Failure(err)) => Err(unparsed(err.input)),
+    Err(nom::Err::Incomplete(_)) => Err(unparsed(input)),
+  }
+}
+
+struct Cave {
+  cells: HashMap<PathPoint, Cell>,
+  sources: Vec<PathPoint>,
+  lowest_rock: Option<i64>,
+  floor: Option<i64>,
+  grains: usize,
+  snapshot_every: Option<usize>,
+  snapshots: Vec<(usize, String)>,
+}
+
+impl Cave {
+  fn from_input(input: &str, sources: &[PathPoint], with_bottom: bool) -> Result<Self, CaveError> {
+    let structures = parse_structures(input)?;
+    let deepest_source = sources
+      .iter()
+      .map(|source| source.1)
+      .max()
+      .ok_or(CaveError::NoSources)?;
+    let cells: HashMap<PathPoint, Cell> = structures
+      .iter()
+      .flat_map(|s| fill_structure(s))
+      .map(|point| (point, Cell::Rock))
+      .collect();
+    let lowest_rock = cells.keys().map(|point| point.1).max();
+    let floor = with_bottom
+      .then(|| lowest_rock.map_or(deepest_source, |lowest| lowest.max(deepest_source)) + 2);
+    Ok(Self {
+      cells,
+      sources: sources.to_vec(),
+      lowest_rock,
+      floor,
+      grains: 0,
+      snapshot_every: None,
+      snapshots: Vec::new(),
+    })
+  }
+  fn cell(&self, point: PathPoint) -> Cell {
+    if self.floor == Some(point.1) {
+      return Cell::Rock;
+    }
+    match self.cells.get(&point) {
+      Some(&cell) => cell,
+      None if self.sources.contains(&point) => Cell::Source,
+      None => Cell::Air,
+    }
+  }
+  fn is_free(&self, point: PathPoint) -> bool {
+    matches!(self.cell(point), Cell::Air | Cell::Source)
+  }
+  fn settle(&mut self, point: PathPoint) {
+    self.cells.insert(point, Cell::Sand);
+    self.grains += 1;
+    if self
+      .snapshot_every
+      .is_some_and(|every| self.grains.is_multiple_of(every))
+    {
+      self.snapshots.push((self.grains, self.render()));
+    }
+  }
+  fn active_region(&self) -> Option<(PathPoint, PathPoint)> {
+    let points = self.cells.keys().chain(&self.sources);
+    let min_x = points.clone().map(|point| point.0).min()?;
+    let max_x = points.clone().map(|point| point.0).max()?;
+    let min_y = points.clone().map(|point| point.1).min()?;
+    let max_y = points.map(|point| point.1).max()?;
+    Some(((min_x, min_y), (max_x, self.floor.unwrap_or(max_y))))
+  }
+  fn render(&self) -> String {
+    let Some(((min_x, min_y), (max_x, max_y))) = self.active_region() else {
+      return String::new();
+    };
+    (min_y..=max_y)
+      .map(|y| {
+        (min_x..=max_x)
+          .map(|x| self.cell((x, y)).symbol())
+          .collect::<String>()
+      })
+      .collect::<Vec<String>>()
+      .join("\n")
+  }
+  fn falls_into_abyss(&self, (_, y): PathPoint) -> bool {
+    self.floor.is_none() && self.lowest_rock.is_none_or(|lowest| y >= lowest)
+  }
+  fn next_step(&self, (x, y): PathPoint) -> Option<PathPoint> {
+    [x, x - 1, x + 1]
+      .into_iter()
+      .map(|next_x| (next_x, y + 1))
+      .find(|&next| self.is_free(next))
+  }
+  fn drop_grain(&self, source: PathPoint) -> Option<PathPoint> {
+    let mut point = source;
+    loop {
+      if self.falls_into_abyss(point) {
+        return None;
+      }
+      match self.next_step(point) {
+        Some(next) => point = next,
+        None => return Some(point),
+      }
+    }
+  }
+  fn open_sources(&self) -> Vec<PathPoint> {
+    self
+      .sources
+      .iter()
+      .copied()
+      .filter(|&source| self.is_free(source))
+      .collect()
+  }
 }
 
-fn simulate_bottomless(grid: &mut Vec<Vec<bool>>, starting_point: PathPoint) -> usize {
-  let max_y = grid[0].len();
-  let mut done = false;
+fn simulate_bottomless(cave: &mut Cave) -> usize {
   let mut resting = 0;
-  while !done {
-    let mut current_point = starting_point;
-    let mut move_possible = true;
-    while move_possible {
-      let (x, y) = current_point;
-      if y + 1 >= max_y {
-        done = true;
-        break;
+  loop {
+    let sources = cave.open_sources();
+    if sources.is_empty() {
+      return resting;
+    }
+    for source in sources {
+      if !cave.is_free(source) {
+        continue;
       }
-      if !grid[x][y + 1] {
-        current_point = (x, y + 1);
-      } else {
-        if x > 0 && !grid[x - 1][y + 1] {
-          current_point = (x - 1, y + 1);
-        } else if x + 1 < grid.len() && !grid[x + 1][y + 1] {
-          current_point = (x + 1, y + 1);
-        } else {
-          grid[current_point.0][current_point.1] = true;
-          move_possible = false;
+      match cave.drop_grain(source) {
+        Some(point) => {
+          cave.settle(point);
           resting += 1;
         }
+        None => return resting,
       }
     }
   }
-  return resting;
 }
 
-fn simulate_with_bottom(grid: &mut Vec<Vec<bool>>, starting_point: PathPoint) -> usize {
-  let mut done = false;
+fn simulate_with_bottom(cave: &mut Cave) -> usize {
   let mut resting = 0;
-  while !done {
-    let mut current_point = starting_point;
-    let mut move_possible = true;
-    while move_possible {
-      let (x, y) = current_point;
-      if !grid[x][y + 1] {
-        current_point = (x, y + 1);
-      } else {
-        if x > 0 && !grid[x - 1][y + 1] {
-          current_point = (x - 1, y + 1);
-        } else if x + 1 < grid.len() && !grid[x + 1][y + 1] {
-          current_point = (x + 1, y + 1);
-        } else {
-          grid[current_point.0][current_point.1] = true;
-          move_possible = false;
-          resting += 1;
+  loop {
+    let sources = cave.open_sources();
+    if sources.is_empty() {
+      return resting;
+    }
+    for source in sources {
+      if cave.is_free(source) {
+        let point = cave
+          .drop_grain(source)
+          .expect("sand fell through the floor");
+        cave.settle(point);
+        resting += 1;
+      }
+    }
+  }
+}
+
+fn simulate_path_stack(cave: &mut Cave) -> usize {
+  let mut paths: Vec<Vec<PathPoint>> = cave.sources.iter().map(|&source| vec![source]).collect();
+  let mut resting = 0;
+  loop {
+    let open: Vec<usize> = (0..paths.len())
+      .filter(|&i| cave.is_free(cave.sources[i]))
+      .collect();
+    if open.is_empty() {
+      return resting;
+    }
+    for i in open {
+      let source = cave.sources[i];
+      if !cave.is_free(source) {
+        continue;
+      }
+      let path = &mut paths[i];
+      if path.is_empty() {
+        path.push(source);
+      }
+      let mut point = *path.last().unwrap();
+      while let Some(next) = cave.next_step(point) {
+        if cave.falls_into_abyss(point) {
+          return resting;
         }
+        path.push(next);
+        point = next;
       }
+      if cave.falls_into_abyss(point) {
+        return resting;
+      }
+      cave.settle(point);
+      resting += 1;
+      for path in paths.iter_mut() {
+        if let Some(pos) = path.iter().rposition(|&visited| visited == point) {
+          path.truncate(pos);
+        }
+      }
+    }
+  }
+}
+
+fn count_floor_fill(cave: &Cave) -> usize {
+  let floor = cave.floor.expect("the analytic solver needs a floor");
+  let mut rocks_by_row: HashMap<i64, HashSet<i64>> = HashMap::new();
+  for (&(x, y), &cell) in &cave.cells {
+    if cell == Cell::Rock {
+      rocks_by_row.entry(y).or_default().insert(x);
     }
-    done = grid[starting_point.0][starting_point.1];
   }
-  return resting;
+  let Some(top) = cave.sources.iter().map(|source| source.1).min() else {
+    return 0;
+  };
+  let mut reachable: HashSet<i64> = HashSet::new();
+  let mut count = 0;
+  for y in top..floor {
+    let rocks = rocks_by_row.get(&y);
+    let free = |x: &i64| rocks.is_none_or(|rocks| !rocks.contains(x));
+    let mut next: HashSet<i64> = reachable
+      .iter()
+      .flat_map(|&x| [x - 1, x, x + 1])
+      .filter(free)
+      .collect();
+    next.extend(
+      cave
+        .sources
+        .iter()
+        .filter(|source| source.1 == y)
+        .map(|source| source.0)
+        .filter(free),
+    );
+    count += next.len();
+    reachable = next;
+  }
+  count
+}
+
+type Simulator = fn(&mut Cave) -> usize;
+
+fn benchmark(input: &str, sources: &[PathPoint]) -> String {
+  let time = |with_bottom: bool, simulate: Simulator| {
+    let mut cave = Cave::from_input(input, sources, with_bottom).unwrap();
+    let start = std::time::Instant::now();
+    let resting = simulate(&mut cave);
+    (resting, start.elapsed())
+  };
+  let runs: [(&str, bool, Simulator); 5] = [
+    ("restart (abyss)", false, simulate_bottomless),
+    ("path stack (abyss)", false, simulate_path_stack),
+    ("restart (floor)", true, simulate_with_bottom),
+    ("path stack (floor)", true, simulate_path_stack),
+    ("row count (floor)", true, |cave| count_floor_fill(cave)),
+  ];
+  runs
+    .iter()
+    .map(|(name, with_bottom, simulate)| {
+      let (resting, elapsed) = time(*with_bottom, *simulate);
+      format!("{:<20} {:>8} grains {:>12.3?}", name, resting, elapsed)
+    })
+    .collect::<Vec<String>>()
+    .join("\n")
+}
+
+fn write_png<W: Write>(frame: &str, writer: W, scale: u32) -> Result<(), png::EncodingError> {
+  let rows: Vec<Vec<Cell>> = frame
+    .lines()
+    .map(|line| line.chars().map(Cell::from_symbol).collect())
+    .collect();
+  let width = rows.iter().map(Vec::len).max().unwrap_or(0) as u32 * scale;
+  let height = rows.len() as u32 * scale;
+  let mut encoder = png::Encoder::new(writer, width, height);
+  encoder.set_color(png::ColorType::Rgb);
+  encoder.set_depth(png::BitDepth::Eight);
+  let mut data = Vec::with_capacity((width * height * 3) as usize);
+  for row in &rows {
+    let line: Vec<u8> = row
+      .iter()
+      .flat_map(|cell| std::iter::repeat_n(cell.color(), scale as usize).flatten())
+      .collect();
+    for _ in 0..scale {
+      data.extend(&line);
+    }
+  }
+  encoder.write_header()?.write_image_data(&data)
+}
+
+fn parse_sources(sources: &str) -> Vec<PathPoint> {
+  sources
+    .split(';')
+    .filter(|source| !source.trim().is_empty())
+    .map(|source| {
+      let (x, y) = source.split_once(',').expect("source must be x,y");
+      (x.trim().parse().unwrap(), y.trim().parse().unwrap())
+    })
+    .collect()
 }
 
 fn part_one(input: &str) -> usize {
-  let mut grid = make_grid(input, false);
-  let result = simulate_bottomless(&mut grid, (500, 0));
-  return result;
+  let mut cave = Cave::from_input(input, &[(500, 0)], false).unwrap();
+  simulate_path_stack(&mut cave)
 }
 
 fn part_two(input: &str) -> usize {
-  let mut grid = make_grid(input, true);
-  let result = simulate_with_bottom(&mut grid, (500, 0));
-  return result;
+  let cave = Cave::from_input(input, &[(500, 0)], true).unwrap();
+  count_floor_fill(&cave)
+}
+
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+  let pos = args.iter().position(|arg| arg == name)?;
+  args.get(pos + 1).map(String::as_str)
 }
 
 fn main() {
@@ -133,6 +412,54 @@ fn main() {
   println!("{}", part_one(input));
   println!();
   println!("{}", part_two(input));
+
+  let args: Vec<String> = std::env::args().skip(1).collect();
+  let sources = parse_sources(option_value(&args, "--sources").unwrap_or("500,0"));
+  let with_bottom = args.iter().any(|arg| arg == "--floor");
+  let mut cave = match Cave::from_input(input, &sources, with_bottom) {
+    Ok(cave) => cave,
+    Err(err) => {
+      eprintln!("{}", err);
+      return;
+    }
+  };
+  cave.snapshot_every = option_value(&args, "--snapshots").map(|every| every.parse().unwrap());
+  let resting = match (with_bottom, args.iter().any(|arg| arg == "--restart")) {
+    (true, true) => simulate_with_bottom(&mut cave),
+    (false, true) => simulate_bottomless(&mut cave),
+    _ => simulate_path_stack(&mut cave),
+  };
+  if option_value(&args, "--sources").is_some() {
+    println!();
+    println!("{}", resting);
+  }
+  if let Some(dir) = option_value(&args, "--png-dir") {
+    let scale = option_value(&args, "--scale").map_or(4, |scale| scale.parse().unwrap());
+    std::fs::create_dir_all(dir).unwrap();
+    let frames = cave
+      .snapshots
+      .iter()
+      .cloned()
+      .chain([(resting, cave.render())]);
+    for (grains, frame) in frames {
+      let file = std::fs::File::create(format!("{}/sand-{:06}.png", dir, grains)).unwrap();
+      write_png(&frame, file, scale).unwrap();
+    }
+  } else if cave.snapshot_every.is_some() {
+    for (grains, frame) in &cave.snapshots {
+      println!();
+      println!("== after {} grains ==", grains);
+      println!("{}", frame);
+    }
+  }
+  if args.iter().any(|arg| arg == "--render") {
+    println!();
+    println!("{}", cave.render());
+  }
+  if args.iter().any(|arg| arg == "--bench") {
+    println!();
+    println!("{}", benchmark(input, &sources));
+  }
 }
 
 #[cfg(test)]
@@ -150,4 +477,145 @@ mod test {
   fn part_two_example() {
     assert_eq!(part_two(INPUT), 93);
   }
+
+  #[test]
+  fn caves_without_rocks() {
+    let sources = [(500, 0)];
+    let mut cave = Cave::from_input("", &sources, true).unwrap();
+    assert_eq!(cave.floor, Some(2));
+    assert_eq!(count_floor_fill(&cave), 1 + 3);
+    assert_eq!(simulate_path_stack(&mut cave), 1 + 3);
+    let mut cave = Cave::from_input("\n", &[(0, 5), (10, 7)], false).unwrap();
+    assert_eq!(simulate_path_stack(&mut cave), 0);
+    assert_eq!(
+      Cave::from_input(INPUT, &parse_sources(""), true).err(),
+      Some(CaveError::NoSources)
+    );
+    assert_eq!(
+      Cave::from_input("498,4 -> 498,6\n503,4 -> x", &sources, true).err(),
+      Some(CaveError::Parse("503,4 -> x".to_string()))
+    );
+    assert_eq!(
+      Cave::from_input("oops", &sources, false)
+        .err()
+        .map(|err| err.to_string()),
+      Some("could not parse rock path at `oops`".to_string())
+    );
+  }
+
+  #[test]
+  fn unbounded_coordinates() {
+    let shifted = "-1000000000002,-3 -> -1000000000002,-1 -> -1000000000004,-1
+-999999999997,-3 -> -999999999998,-3 -> -999999999998,2 -> -1000000000006,2";
+    let source = [(-1_000_000_000_000, -7)];
+    assert_eq!(
+      simulate_bottomless(&mut Cave::from_input(shifted, &source, false).unwrap()),
+      24
+    );
+    assert_eq!(
+      simulate_with_bottom(&mut Cave::from_input(shifted, &source, true).unwrap()),
+      93
+    );
+  }
+
+  #[test]
+  fn exact_floor() {
+    let narrow = "0,1 -> 0,20";
+    let mut cave = Cave::from_input(narrow, &[(0, 0)], true).unwrap();
+    assert_eq!(cave.floor, Some(22));
+    let rows: usize = (0..22).map(|y| 2 * y + 1).sum();
+    assert_eq!(simulate_with_bottom(&mut cave), rows - 20);
+  }
+
+  #[test]
+  fn multiple_sources() {
+    let sources = parse_sources("0,0;100,0");
+    let mut cave = Cave::from_input("0,2 -> 0,2", &sources, true).unwrap();
+    assert_eq!(simulate_with_bottom(&mut cave), 15 + 16);
+    let mut cave = Cave::from_input("0,2 -> 0,2", &sources, false).unwrap();
+    assert_eq!(simulate_bottomless(&mut cave), 0);
+  }
+
+  #[test]
+  fn render_example() {
+    let mut cave = Cave::from_input(INPUT, &[(500, 0)], false).unwrap();
+    assert_eq!(
+      cave.render(),
+      "......+...
+..........
+..........
+..........
+....#...##
+....#...#.
+..###...#.
+........#.
+........#.
+#########."
+    );
+    cave.snapshot_every = Some(5);
+    assert_eq!(simulate_bottomless(&mut cave), 24);
+    assert_eq!(
+      cave
+        .snapshots
+        .iter()
+        .map(|(grains, _)| *grains)
+        .collect::<Vec<_>>(),
+      vec![5, 10, 15, 20]
+    );
+    assert_eq!(cave.snapshots[0].1.lines().nth(7), Some("......o.#."));
+    assert_eq!(
+      cave.render(),
+      "......+...
+..........
+......o...
+.....ooo..
+....#ooo##
+...o#ooo#.
+..###ooo#.
+....oooo#.
+.o.ooooo#.
+#########."
+    );
+    let mut png = Vec::new();
+    write_png(&cave.render(), &mut png, 2).unwrap();
+    assert_eq!(&png[1..4], b"PNG");
+  }
+
+  #[test]
+  fn render_with_floor() {
+    let mut cave = Cave::from_input(INPUT, &[(500, 0)], true).unwrap();
+    simulate_with_bottom(&mut cave);
+    let picture = cave.render();
+    let lines: Vec<&str> = picture.lines().collect();
+    assert_eq!(lines.len(), 12);
+    assert_eq!(lines[0], "..........o..........");
+    assert!(lines[11].chars().all(|c| c == '#'));
+  }
+
+  #[test]
+  fn fast_simulators_agree() {
+    let shifted = "-1000000000002,-3 -> -1000000000002,-1 -> -1000000000004,-1
+-999999999997,-3 -> -999999999998,-3 -> -999999999998,2 -> -1000000000006,2";
+    let cases = [
+      (INPUT, parse_sources("500,0")),
+      (INPUT, parse_sources("500,0;497,1;510,-4")),
+      (shifted, parse_sources("-1000000000000,-7")),
+      ("0,2 -> 0,2", parse_sources("0,0;100,0")),
+      ("0,1 -> 0,20", parse_sources("0,0")),
+    ];
+    for (input, sources) in cases {
+      let restart = simulate_bottomless(&mut Cave::from_input(input, &sources, false).unwrap());
+      let mut cave = Cave::from_input(input, &sources, false).unwrap();
+      assert_eq!(simulate_path_stack(&mut cave), restart);
+      let mut restarted = Cave::from_input(input, &sources, true).unwrap();
+      let restart = simulate_with_bottom(&mut restarted);
+      let mut cave = Cave::from_input(input, &sources, true).unwrap();
+      assert_eq!(simulate_path_stack(&mut cave), restart);
+      assert_eq!(cave.render(), restarted.render());
+      assert_eq!(
+        count_floor_fill(&Cave::from_input(input, &sources, true).unwrap()),
+        restart
+      );
+    }
+  }
 }
@@ -0,0 +1,206 @@
+use crate::{coverage_at, GridPosition, Report, SearchArea};
+use std::cmp::{max, min};
+use std::io::{self, Write};
+
+const COVERED: u64 = 176;
+const BEACON: u8 = 216;
+const SENSOR: u8 = 255;
+
+type Rectangle = ((i64, i64), (i64, i64));
+
+fn rotated(report: &Report) -> Rectangle {
+  let (x, y) = (
+    report.sensor_position.0 as i64,
+    report.sensor_position.1 as i64,
+  );
+  let radius = report.radius() as i64;
+  (
+    (x + y - radius, x + y + radius + 1),
+    (x - y - radius, x - y + radius + 1),
+  )
+}
+
+fn bounds(ranges: impl Iterator<Item = (i64, i64)>) -> Vec<i64> {
+  let mut bounds: Vec<i64> = ranges.flat_map(|(start, end)| [start, end]).collect();
+  bounds.sort();
+  bounds.dedup();
+  bounds
+}
+
+fn evens(start: i64, end: i64) -> u64 {
+  ((end + 1).div_euclid(2) - (start + 1).div_euclid(2)) as u64
+}
+
+pub fn covered_area(reports: &[Report]) -> u64 {
+  let rectangles: Vec<Rectangle> = reports.iter().map(rotated).collect();
+  let us = bounds(rectangles.iter().map(|&(u, _)| u));
+  let vs = bounds(rectangles.iter().map(|&(_, v)| v));
+  let mut area = 0;
+  for u in us.windows(2) {
+    for v in vs.windows(2) {
+      let covered = rectangles
+        .iter()
+        .any(|&((u0, u1), (v0, v1))| u0 <= u[0] && u[1] <= u1 && v0 <= v[0] && v[1] <= v1);
+      if covered {
+        let (even_u, even_v) = (evens(u[0], u[1]), evens(v[0], v[1]));
+        let (odd_u, odd_v) = ((u[1] - u[0]) as u64 - even_u, (v[1] - v[0]) as u64 - even_v);
+        area += even_u * even_v + odd_u * odd_v;
+      }
+    }
+  }
+  area
+}
+
+pub fn uncovered_cells(reports: &[Report], area: &SearchArea) -> Vec<GridPosition> {
+  let mut cells = Vec::new();
+  for y in area.min.1..=area.max.1 {
+    let mut x = area.min.0;
+    for (start, end) in coverage_at(reports, y) {
+      cells.extend((x..min(start, area.max.0.saturating_add(1))).map(|x| (x, y)));
+      x = max(x, end.saturating_add(1));
+      if x > area.max.0 {
+        break;
+      }
+    }
+    cells.extend((x..=area.max.0).map(|x| (x, y)));
+  }
+  cells
+}
+
+pub struct CoverageImage {
+  pub width: usize,
+  pub height: usize,
+  pub pixels: Vec<u8>,
+}
+
+impl CoverageImage {
+  pub fn write_pgm<W: Write>(&self, mut writer: W) -> io::Result<()> {
+    write!(writer, "P5\n{} {}\n255\n", self.width, self.height)?;
+    writer.write_all(&self.pixels)
+  }
+
+  pub fn write_png<W: Write>(&self, writer: W) -> Result<(), png::EncodingError> {
+    let mut encoder = png::Encoder::new(writer, self.width as u32, self.height as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&self.pixels)
+  }
+}
+
+pub fn render_coverage(reports: &[Report], area: &SearchArea, scale: u32) -> CoverageImage {
+  let scale = scale.max(1) as i64;
+  let (min_x, min_y) = (area.min.0 as i64, area.min.1 as i64);
+  let (columns, rows) = (area.max.0 as i64 - min_x + 1, area.max.1 as i64 - min_y + 1);
+  let width = (columns + scale - 1) / scale;
+  let height = (rows + scale - 1) / scale;
+  let mut pixels = Vec::with_capacity((width * height) as usize);
+  for row in 0..height {
+    let y = min(min_y + row * scale + scale / 2, area.max.1 as i64);
+    let mut covered = vec![0; width as usize];
+    for (start, end) in coverage_at(reports, y as i32) {
+      let start = max(start as i64, min_x) - min_x;
+      let end = min(end as i64, area.max.0 as i64) - min_x;
+      for column in (start / scale)..=(end / scale).min(width - 1) {
+        let block_start = max(start, column * scale);
+        let block_end = min(end, column * scale + scale - 1);
+        covered[column as usize] += max(0, block_end - block_start + 1) as u64;
+      }
+    }
+    pixels.extend(covered.iter().enumerate().map(|(column, &cells)| {
+      let block = min(scale, columns - column as i64 * scale) as u64;
+      (cells * COVERED / block) as u8
+    }));
+  }
+  let mut image = CoverageImage {
+    width: width as usize,
+    height: height as usize,
+    pixels,
+  };
+  let markers = reports
+    .iter()
+    .map(|r| (r.beacon_position, BEACON))
+    .chain(reports.iter().map(|r| (r.sensor_position, SENSOR)));
+  for (position, shade) in markers {
+    if area.contains(position) {
+      let x = (position.0 as i64 - min_x) / scale;
+      let y = (position.1 as i64 - min_y) / scale;
+      image.pixels[(y * width + x) as usize] = shade;
+    }
+  }
+  image
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn pixel(image: &CoverageImage, x: usize, y: usize) -> u8 {
+    image.pixels[y * image.width + x]
+  }
+
+  fn overlapping() -> Vec<Report> {
+    vec![
+      Report {
+        sensor_position: (0, 0),
+        beacon_position: (2, 0),
+      },
+      Report {
+        sensor_position: (2, 0),
+        beacon_position: (4, 0),
+      },
+    ]
+  }
+
+  #[test]
+  fn union_of_diamonds() {
+    let reports = overlapping();
+    assert_eq!(covered_area(&reports[..1]), 13);
+    assert_eq!(covered_area(&reports), 13 + 13 - 5);
+    let area = SearchArea {
+      min: (-3, -1),
+      max: (5, 1),
+    };
+    assert_eq!(
+      uncovered_cells(&reports, &area),
+      vec![
+        (-3, -1),
+        (-2, -1),
+        (4, -1),
+        (5, -1),
+        (-3, 0),
+        (5, 0),
+        (-3, 1),
+        (-2, 1),
+        (4, 1),
+        (5, 1)
+      ]
+    );
+  }
+
+  #[test]
+  fn coverage_image() {
+    let reports = overlapping();
+    let area = SearchArea {
+      min: (-2, -2),
+      max: (4, 2),
+    };
+    let image = render_coverage(&reports, &area, 1);
+    assert_eq!((image.width, image.height), (7, 5));
+    assert_eq!(pixel(&image, 0, 0), 0);
+    assert_eq!(pixel(&image, 1, 1), COVERED as u8);
+    assert_eq!(pixel(&image, 2, 2), SENSOR);
+    assert_eq!(pixel(&image, 4, 2), SENSOR);
+    assert_eq!(pixel(&image, 6, 2), BEACON);
+    let image = render_coverage(&reports, &area, 2);
+    assert_eq!((image.width, image.height), (4, 3));
+    assert_eq!(pixel(&image, 0, 0), COVERED as u8 / 2);
+    assert_eq!(pixel(&image, 3, 0), 0);
+    let mut pgm = Vec::new();
+    image.write_pgm(&mut pgm).unwrap();
+    assert!(pgm.starts_with(b"P5\n4 3\n255\n"));
+    assert_eq!(pgm.len(), 11 + 12);
+    let mut png = Vec::new();
+    image.write_png(&mut png).unwrap();
+    assert_eq!(&png[1..4], b"PNG");
+  }
+}
@@ -1,4 +1,8 @@
+mod coverage;
+
+use coverage::{covered_area, render_coverage, uncovered_cells};
 use std::cmp::max;
+use std::collections::HashSet;
 use nom::combinator::map;
 use nom::sequence::separated_pair;
 use nom::Parser;
@@ -14,6 +18,24 @@ type GridPosition = (i32, i32);
 
 type Interval = (i32, i32);
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SearchArea {
+  min: GridPosition,
+  max: GridPosition,
+}
+
+impl SearchArea {
+  fn square(limit: i32) -> Self {
+    Self {
+      min: (0, 0),
+      max: (limit, limit),
+    }
+  }
+  fn contains(&self, (x, y): GridPosition) -> bool {
+    (self.min.0..=self.max.0).contains(&x) && (self.min.1..=self.max.1).contains(&y)
+  }
+}
+
 #[derive(Debug)]
 struct Report {
   sensor_position: GridPosition,
@@ -21,18 +43,33 @@ struct Report {
 }
 
 impl Report {
+  fn radius(&self) -> i32 {
+    (self.sensor_position.0 - self.beacon_position.0).abs()
+      + (self.sensor_position.1 - self.beacon_position.1).abs()
+  }
+  fn covers(&self, position: GridPosition) -> bool {
+    (self.sensor_position.0 - position.0).abs() + (self.sensor_position.1 - position.1).abs()
+      <= self.radius()
+  }
+  fn boundary_lines(&self) -> ([i64; 2], [i64; 2]) {
+    let (x, y) = (self.sensor_position.0 as i64, self.sensor_position.1 as i64);
+    let outside = self.radius() as i64 + 1;
+    (
+      [x + y - outside, x + y + outside],
+      [x - y - outside, x - y + outside],
+    )
+  }
   fn unused_interval_at(&self, y: i32) -> Option<Interval> {
-    let radius = (self.sensor_position.0 - self.beacon_position.0).abs()
-      + (self.sensor_position.1 - self.beacon_position.1).abs();
+    let radius = self.radius();
     let dy_sensor_to_y = (self.sensor_position.1 - y).abs();
     if dy_sensor_to_y > radius {
       return None;
     }
     let dx_sensor_to_edge = radius - dy_sensor_to_y;
-    return Some((
+    Some((
       self.sensor_position.0 - dx_sensor_to_edge,
       self.sensor_position.0 + dx_sensor_to_edge,
-    ));
+    ))
   }
 }
 
@@ -62,11 +99,14 @@ fn reports(input: &str) -> IResult<&str, Vec<Report>> {
   .parse(input)
 }
 
-fn merge_intervals(intervals: &mut Vec<Interval>) -> Vec<Interval> {
+fn merge_intervals(intervals: &[Interval]) -> Vec<Interval> {
   let mut result: Vec<Interval> = Vec::new();
-  let mut last_interval: Interval = *intervals.get(0).unwrap();
-  for interval in intervals.into_iter().skip(1) {
-    if interval.0 <= last_interval.1 {
+  let Some(&first) = intervals.first() else {
+    return result;
+  };
+  let mut last_interval: Interval = first;
+  for interval in intervals.iter().skip(1) {
+    if interval.0 <= last_interval.1 + 1 {
       last_interval.1 = max(interval.1, last_interval.1);
     } else {
       result.push(last_interval);
@@ -74,48 +114,143 @@ fn merge_intervals(intervals: &mut Vec<Interval>) -> Vec<Interval> {
     }
   }
   result.push(last_interval);
-  return result;
+  result
 }
 
-fn part_one(input: &str, y: i32) -> usize {
-  let (_, reports) = reports(input).unwrap();
+fn coverage_at(reports: &[Report], y: i32) -> Vec<Interval> {
   let mut intervals: Vec<Interval> = reports
     .iter()
     .filter_map(|r| r.unused_interval_at(y))
     .collect();
   intervals.sort();
-  let merged_intervals = merge_intervals(&mut intervals);
-  merged_intervals
+  merge_intervals(&intervals)
+}
+
+fn covered_positions(reports: &[Report], y: i32) -> usize {
+  let coverage = coverage_at(reports, y);
+  let covered: usize = coverage
     .iter()
-    .map(|int| (int.0 - int.1).abs() as usize)
-    .sum()
+    .map(|int| (int.1 - int.0 + 1) as usize)
+    .sum();
+  let beacons: HashSet<GridPosition> = reports
+    .iter()
+    .map(|r| r.beacon_position)
+    .filter(|beacon| beacon.1 == y)
+    .filter(|beacon| {
+      coverage
+        .iter()
+        .any(|int| (int.0..=int.1).contains(&beacon.0))
+    })
+    .collect();
+  covered - beacons.len()
 }
 
-fn part_two(input: &str, limit: i32) -> u64 {
+fn part_one(input: &str, y: i32) -> usize {
   let (_, reports) = reports(input).unwrap();
-  for y in 0..limit {
-    let mut intervals: Vec<Interval> = reports
-      .iter()
-      .filter_map(|r| r.unused_interval_at(y))
-      .collect();
-    intervals.sort();
-    let merged_intervals = merge_intervals(&mut intervals);
-    let beacon_position = merged_intervals
-      .iter()
-      .map(|(_, end)| end)
-      .find_map(|&e| {
-        if e >= -1 && e <= limit - 1 {
-          return Some(e + 1);
-        }
-        None
-      });
-    if beacon_position.is_some() {
-      let res_x: u64 = beacon_position.unwrap().try_into().unwrap();
-      let res_y: u64 = y.try_into().unwrap();
-      return 4000000 * res_x + res_y;
+  covered_positions(&reports, y)
+}
+
+fn first_gap(coverage: &[Interval], from: i32, to: i32) -> Option<i32> {
+  let mut x = from;
+  for &(start, end) in coverage {
+    if start > x {
+      break;
     }
+    x = max(x, end.saturating_add(1));
+  }
+  (x <= to).then_some(x)
+}
+
+fn distress_beacon_by_rows(reports: &[Report], area: &SearchArea) -> Option<GridPosition> {
+  (area.min.1..=area.max.1)
+    .find_map(|y| first_gap(&coverage_at(reports, y), area.min.0, area.max.0).map(|x| (x, y)))
+}
+
+fn distress_beacon_by_diamonds(reports: &[Report], area: &SearchArea) -> Option<GridPosition> {
+  let (min_x, min_y) = (area.min.0 as i64, area.min.1 as i64);
+  let (max_x, max_y) = (area.max.0 as i64, area.max.1 as i64);
+  let (mut u_lines, mut v_lines): (Vec<i64>, Vec<i64>) = (Vec::new(), Vec::new());
+  for report in reports {
+    let (u, v) = report.boundary_lines();
+    u_lines.extend(u);
+    v_lines.extend(v);
+  }
+  let crossings = u_lines.iter().flat_map(|&u| {
+    v_lines
+      .iter()
+      .filter(move |&&v| (u + v) % 2 == 0)
+      .map(move |&v| ((u + v) / 2, (u - v) / 2))
+  });
+  let border_u = u_lines.iter().flat_map(|&u| {
+    [
+      (min_x, u - min_x),
+      (max_x, u - max_x),
+      (u - min_y, min_y),
+      (u - max_y, max_y),
+    ]
+  });
+  let border_v = v_lines.iter().flat_map(|&v| {
+    [
+      (min_x, min_x - v),
+      (max_x, max_x - v),
+      (v + min_y, min_y),
+      (v + max_y, max_y),
+    ]
+  });
+  let corners = [
+    (min_x, min_y),
+    (min_x, max_y),
+    (max_x, min_y),
+    (max_x, max_y),
+  ];
+  crossings
+    .chain(border_u)
+    .chain(border_v)
+    .chain(corners)
+    .filter(|&(x, y)| (min_x..=max_x).contains(&x) && (min_y..=max_y).contains(&y))
+    .map(|(x, y)| (x as i32, y as i32))
+    .find(|&position| area.contains(position) && reports.iter().all(|r| !r.covers(position)))
+}
+
+const TUNING_MULTIPLIER: i64 = 4000000;
+
+fn tuning_frequency((x, y): GridPosition, multiplier: i64) -> i64 {
+  multiplier * x as i64 + y as i64
+}
+
+fn find_distress_beacon(
+  reports: &[Report],
+  area: &SearchArea,
+  row_scan: bool,
+) -> Option<GridPosition> {
+  if row_scan {
+    distress_beacon_by_rows(reports, area)
+  } else {
+    distress_beacon_by_diamonds(reports, area)
+  }
+}
+
+fn part_two(input: &str, limit: i32) -> i64 {
+  let (_, reports) = reports(input).unwrap();
+  let position = find_distress_beacon(&reports, &SearchArea::square(limit), false)
+    .expect("no distress beacon found");
+  tuning_frequency(position, TUNING_MULTIPLIER)
+}
+
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+  let pos = args.iter().position(|arg| arg == name)?;
+  args.get(pos + 1).map(String::as_str)
+}
+
+fn parse_area(area: &str) -> SearchArea {
+  let corners: Vec<i32> = area.split(',').map(|c| c.trim().parse().unwrap()).collect();
+  match corners[..] {
+    [min_x, min_y, max_x, max_y] => SearchArea {
+      min: (min_x, min_y),
+      max: (max_x, max_y),
+    },
+    _ => panic!("area must be min_x,min_y,max_x,max_y"),
   }
-  todo!("return")
 }
 
 fn main() {
@@ -123,6 +258,46 @@ fn main() {
   println!("{}", part_one(input, 2000000));
   println!();
   println!("{}", part_two(input, 4000000));
+
+  let args: Vec<String> = std::env::args().skip(1).collect();
+  let (_, reports) = reports(input).unwrap();
+  if let Some(y) = option_value(&args, "--row") {
+    println!();
+    println!("{}", covered_positions(&reports, y.parse().unwrap()));
+  }
+  let area = option_value(&args, "--area").map_or(SearchArea::square(4000000), parse_area);
+  let multiplier = option_value(&args, "--multiplier")
+    .map_or(TUNING_MULTIPLIER, |multiplier| multiplier.parse().unwrap());
+  let row_scan = args.iter().any(|arg| arg == "--row-scan");
+  if option_value(&args, "--area").is_some() || row_scan {
+    println!();
+    match find_distress_beacon(&reports, &area, row_scan) {
+      Some(position) => println!("{}", tuning_frequency(position, multiplier)),
+      None => println!("every position in the area is covered"),
+    }
+  }
+  if args.iter().any(|arg| arg == "--covered-area") {
+    println!();
+    println!("{}", covered_area(&reports));
+  }
+  if let Some(bounds) = option_value(&args, "--uncovered") {
+    println!();
+    for (x, y) in uncovered_cells(&reports, &parse_area(bounds)) {
+      println!("{},{}", x, y);
+    }
+  }
+  if let Some(path) = option_value(&args, "--map") {
+    let width = area.max.0 as i64 - area.min.0 as i64 + 1;
+    let scale =
+      option_value(&args, "--scale").map_or((width + 399) / 400, |scale| scale.parse().unwrap());
+    let image = render_coverage(&reports, &area, scale as u32);
+    let file = std::fs::File::create(path).unwrap();
+    if path.ends_with(".pgm") {
+      image.write_pgm(file).unwrap();
+    } else {
+      image.write_png(file).unwrap();
+    }
+  }
 }
 
 #[cfg(test)]
@@ -172,4 +347,86 @@ Sensor at x=20, y=1: closest beacon is at x=15, y=3";
       assert_eq!(report.unused_interval_at(y), Some(i));
     }
   }
+
+  #[test]
+  fn diamond_solver_matches_row_scan() {
+    let (_, example) = reports(INPUT).unwrap();
+    let area = SearchArea::square(20);
+    assert_eq!(distress_beacon_by_diamonds(&example, &area), Some((14, 11)));
+    assert_eq!(
+      distress_beacon_by_diamonds(&example, &area),
+      distress_beacon_by_rows(&example, &area)
+    );
+    let corner = "Sensor at x=2, y=2: closest beacon is at x=2, y=5
+Sensor at x=4, y=0: closest beacon is at x=4, y=1
+Sensor at x=0, y=4: closest beacon is at x=0, y=3
+Sensor at x=4, y=4: closest beacon is at x=4, y=3";
+    let (_, corner) = reports(corner).unwrap();
+    let area = SearchArea::square(4);
+    assert_eq!(distress_beacon_by_diamonds(&corner, &area), Some((0, 0)));
+    assert_eq!(distress_beacon_by_rows(&corner, &area), Some((0, 0)));
+  }
+
+  #[test]
+  fn merging_adjacent_intervals() {
+    assert_eq!(merge_intervals(&[(1, 3), (4, 6)]), vec![(1, 6)]);
+    assert_eq!(merge_intervals(&[(1, 3), (2, 8), (5, 6)]), vec![(1, 8)]);
+    assert_eq!(merge_intervals(&[(1, 2), (4, 5)]), vec![(1, 2), (4, 5)]);
+    assert_eq!(merge_intervals(&[]), vec![]);
+  }
+
+  #[test]
+  fn exact_row_coverage() {
+    let (_, example) = reports(INPUT).unwrap();
+    assert_eq!(coverage_at(&example, 10), vec![(-2, 24)]);
+    assert_eq!(covered_positions(&example, 10), 27 - 1);
+    assert_eq!(coverage_at(&example, 16), vec![(-3, 26)]);
+    assert_eq!(covered_positions(&example, 16), 30 - 1);
+    let single = "Sensor at x=0, y=0: closest beacon is at x=2, y=0
+Sensor at x=10, y=0: closest beacon is at x=7, y=0";
+    let (_, single) = reports(single).unwrap();
+    assert_eq!(coverage_at(&single, 0), vec![(-2, 2), (7, 13)]);
+    assert_eq!(covered_positions(&single, 0), 5 + 7 - 2);
+    assert_eq!(covered_positions(&single, 1), 3 + 5);
+  }
+
+  #[test]
+  fn arbitrary_search_area() {
+    let (_, example) = reports(INPUT).unwrap();
+    let shifted = SearchArea {
+      min: (10, 5),
+      max: (20, 12),
+    };
+    assert_eq!(
+      find_distress_beacon(&example, &shifted, false),
+      Some((14, 11))
+    );
+    assert_eq!(
+      find_distress_beacon(&example, &shifted, true),
+      Some((14, 11))
+    );
+    let covered = SearchArea {
+      min: (0, 0),
+      max: (10, 10),
+    };
+    assert_eq!(find_distress_beacon(&example, &covered, false), None);
+    assert_eq!(find_distress_beacon(&example, &covered, true), None);
+    assert_eq!(tuning_frequency((14, 11), 10), 151);
+  }
+
+  #[test]
+  fn example_coverage_map() {
+    let (_, example) = reports(INPUT).unwrap();
+    let rows: u64 = (-20..=50)
+      .flat_map(|y| coverage_at(&example, y))
+      .map(|int| (int.1 - int.0 + 1) as u64)
+      .sum();
+    assert_eq!(covered_area(&example), rows);
+    assert_eq!(
+      uncovered_cells(&example, &SearchArea::square(20)),
+      vec![(14, 11)]
+    );
+    let image = render_coverage(&example, &SearchArea::square(20), 1);
+    assert_eq!(image.pixels[11 * image.width + 14], 0);
+  }
 }